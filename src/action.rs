@@ -1,29 +1,90 @@
+use std::{path::PathBuf, time::Duration};
+
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crossterm::event::{KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use tracing::debug;
 
-use crate::app::{ActionState, ApiQueryResponseState, App, CurrentFocus};
-use crate::port_check::PortState;
+use crate::api::DNSQuery;
+use crate::app::{
+    ActionState, ApiQueryResponseState, App, BlockingState, CurrentFocus, CurrentScreen,
+    DNSQueryState,
+};
+use crate::bulk::{self, BulkResult};
+use crate::config::Config;
+use crate::dialog::ConfirmDialog;
+use crate::keymap::{Command, KeyContext};
+use crate::notification::NotificationLevel;
+use crate::palette::{parse_duration, PaletteCommand, PaletteMatch, MAX_PALETTE_MATCHES};
+use crate::port_check::{AddressProbe, PortState};
+use crate::suite::{CaseResult, Expectation, Outcome, Verdict};
+use crate::tasks::TaskKind;
 use crate::tui::Event;
+use crate::ui::{button_area, contains, palette_area, MainLayout};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Action {
     Init,
     CycleFocusUp,   // Move focus to next tile in UI
     CycleFocusDown, // Move focus to previous tile in UI
     JumpToTile(u8),
-    EnableDNSBlocking,  // enables DNS blocking
-    DisableDNSBlocking, // disable DNS blocking
-    SubmitDNSQuery,     // sends DNS query to blocky
-    RefreshLists,       // Refresh blocking lists
-    UpdateTile,         // Update current Tile (or all app information)
+    EnableDNSBlocking,                    // enables DNS blocking
+    DisableDNSBlocking(Option<Duration>), // disable DNS blocking, optionally only temporary
+    SubmitDNSQuery(DNSQuery),             // sends DNS query to blocky
+    SetDNSQueryState(DNSQueryState),      // result of a DNS query from the query tile or palette
+    StartQueryInput,                      // start typing a domain into the query tile
+    RefreshLists,                         // Refresh blocking lists
+    UpdateTile,                           // Update current Tile (or all app information)
+    UpdateDNSStatus,                      // probe the API and the DNS port
+    UpdateBlockingStatus,                 // query the current blocking status
     ClearDNSCache,
     Key(KeyEvent),
     SetDNSStatus(ApiQueryResponseState),
     SetUDPPortState(PortState),
     SetTCPPortState(PortState),
+    SetUDPAddressStates(Vec<AddressProbe>), // DNS probe results of all server addresses
+    SetTCPAddressStates(Vec<AddressProbe>), // API port probe results of all server addresses
     SetRefreshListState(ActionState),
     SetDNSCacheClearState(ActionState),
+    SetBlockingState(BlockingState),
+    ShowDialog(ConfirmDialog), // asks the user to confirm an action in a modal dialog
+    DialogAccept,              // dispatches the action of the open dialog
+    DialogCancel,              // closes the open dialog without doing anything
+    DialogToggleSelection,     // switch between the yes and no button
+    ToggleHelp,                // show or hide the key binding help overlay
+    CloseScreen,               // go back to the main screen
+    SubmitInput,               // finish typing into the active text input
+    CancelInput,               // discard the active text input
+    OpenPalette,               // show the command palette
+    SelectPaletteEntry(usize), // select a palette match, e.g. by clicking on it
+    Resize(u16, u16),          // the terminal was resized to width and height
+    Paste(String),             // text pasted into the terminal
+    Notify(NotificationLevel, String), // shows a toast and stores it in the event log
+    ToggleEventLog,            // show or hide the event log pane
+    ToggleLogView,             // show or hide the in-app log viewer
+    CycleLogLevel,             // change the minimum level of shown log records
+    ToggleLogFollow,           // follow new log records or freeze the view
+    StartLogSearch,            // start typing a search term in the log viewer
+    ScrollUp,
+    ScrollDown,
+    TaskFinished(TaskKind, u64), // a background task is done or was cancelled
+    Suspend,                     // return to the shell until the process is resumed
+    EditFile(PathBuf, Box<Action>), // open the file in $EDITOR, then run the action
+    ReloadConfig,                // read the config file again
+    StartBulkTest(PathBuf, String), // query all domains of the list with the query type
+    BulkTestLoaded(PathBuf, String, usize), // the domain list was read, its results follow
+    BulkResult(BulkResult),      // result of a single domain of the running bulk test
+    ShowBulkTest,                // show the results of the last bulk test
+    ExportBulkResults(PathBuf),  // write the bulk test results to a CSV or JSON file
+    CycleBulkSort,               // sort the bulk test results by the next column
+    ReverseBulkSort,             // reverse the sort order of the bulk test results
+    StartSuite(PathBuf),         // check all expectations of the suite file
+    SuiteLoaded(PathBuf, usize), // the suite file was read, the results of its checks follow
+    SuiteResult(CaseResult),     // result of a single check of the running suite
+    ShowSuite,                   // show the report of the last test suite
+    CheckWatchlist,              // check all domains of the watchlist
+    WatchResult(Expectation, Verdict), // verdict of a single domain of the watchlist
+    Watch(Expectation),          // add the domain to the watchlist
+    Unwatch(String),             // remove the domain from the watchlist
     Render,
     Quit, // quits application
 }
@@ -36,57 +97,342 @@ impl App {
         match event {
             Event::Init => self.action_tx.send(Action::Init)?,
             Event::Key(key) => self.handle_key(key)?,
-            Event::Quit => self.action_tx.send(Action::Quit)?,
-            Event::Render => self.action_tx.send(Action::Render)?,
+            Event::Mouse(mouse) => self.handle_mouse(mouse)?,
+            Event::Paste(text) => self.action_tx.send(Action::Paste(text.clone()))?,
+            Event::Resize(width, height) => self.action_tx.send(Action::Resize(*width, *height))?,
+            // without input nothing could ever quit the app, e.g. the terminal was closed
+            Event::Quit | Event::Closed => self.action_tx.send(Action::Quit)?,
+            Event::Render => {
+                // ticks drive the schedule of the watchlist, a running round is never duplicated
+                if self.watchlist.is_due() && !self.tasks.is_running(TaskKind::Watchlist) {
+                    self.action_tx.send(Action::CheckWatchlist)?
+                }
+                if self.needs_tick_render() {
+                    self.action_tx.send(Action::Render)?
+                }
+            }
             _ => {}
         }
         Ok(())
     }
 
     fn handle_key(&self, key: &KeyEvent) -> Result<()> {
-        match key.code {
-            KeyCode::Esc => self.action_tx.send(Action::Quit)?,
-            KeyCode::Char('q') => {
-                if !self.is_currently_editing {
-                    self.action_tx.send(Action::Quit)?
-                } else {
-                    self.action_tx.send(Action::Key(*key))?
-                }
+        let context = self.key_context();
+        match self.keymap.get(context, key) {
+            Some(command) => self.handle_command(command)?,
+            // unbound keys are typed into the active text input
+            None if matches!(context, KeyContext::Editing | KeyContext::Palette) => {
+                self.action_tx.send(Action::Key(*key))?
             }
-            KeyCode::Char('c') => {
-                if key.modifiers == KeyModifiers::CONTROL {
-                    self.action_tx.send(Action::Quit)?
-                } else {
-                    self.action_tx.send(Action::Key(*key))?
-                }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn handle_mouse(&self, mouse: &MouseEvent) -> Result<()> {
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.action_tx.send(Action::ScrollUp)?,
+            MouseEventKind::ScrollDown => self.action_tx.send(Action::ScrollDown)?,
+            MouseEventKind::Down(MouseButton::Left) => {
+                self.handle_click(mouse.column, mouse.row)?
             }
-            KeyCode::Enter => {
-                if !self.is_currently_editing {
-                    // dont call updateTile command on the refresh lists tile
-                    if self.current_focus == CurrentFocus::RefreshLists {
-                        self.action_tx.send(Action::RefreshLists)?
-                    } else if self.current_focus == CurrentFocus::DeleteCache {
-                        self.action_tx.send(Action::ClearDNSCache)?
-                    } else {
-                        self.action_tx.send(Action::UpdateTile)?
-                    }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Hit-tests a click against the same areas `App::render` uses, overlays take precedence
+    fn handle_click(&self, x: u16, y: u16) -> Result<()> {
+        if self.show_help {
+            self.action_tx.send(Action::ToggleHelp)?;
+            return Ok(());
+        }
+        if self.dialog.is_some() {
+            let (yes_area, no_area) = ConfirmDialog::button_areas(self.terminal_area);
+            if contains(yes_area, x, y) {
+                self.action_tx.send(Action::DialogAccept)?
+            } else if contains(no_area, x, y) {
+                self.action_tx.send(Action::DialogCancel)?
+            }
+            return Ok(());
+        }
+        if let Some(palette) = &self.palette {
+            let shown = palette.matches().len().min(MAX_PALETTE_MATCHES);
+            let area = palette_area(self.terminal_area, shown);
+            // the matches start below the top border and the input line
+            let first_row = area.y + 2;
+            if contains(area, x, y) && y >= first_row && ((y - first_row) as usize) < shown {
+                self.action_tx
+                    .send(Action::SelectPaletteEntry((y - first_row) as usize))?;
+                self.action_tx.send(Action::SubmitInput)?;
+            }
+            return Ok(());
+        }
+        if self.current_screen != CurrentScreen::Main {
+            return Ok(());
+        }
+
+        let layout = MainLayout::new(self.terminal_area);
+        if let Some(tile) = layout.tile_at(x, y) {
+            self.action_tx
+                .send(Action::JumpToTile(tile.get_tile_number()))?;
+            let on_button = self
+                .tile_button_label(&tile)
+                .is_some_and(|label| contains(button_area(layout.tile_area(&tile), label), x, y));
+            if on_button {
+                self.activate_tile(&tile)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The key bindings which are currently active, the help overlay and dialogs take precedence
+    pub fn key_context(&self) -> KeyContext {
+        if self.show_help {
+            KeyContext::Help
+        } else {
+            self.screen_key_context()
+        }
+    }
+
+    /// The key bindings of the shown screen, ignoring the help overlay
+    pub fn screen_key_context(&self) -> KeyContext {
+        if self.dialog.is_some() {
+            KeyContext::Dialog
+        } else if self.palette.is_some() {
+            KeyContext::Palette
+        } else if self.is_currently_editing {
+            KeyContext::Editing
+        } else {
+            match self.current_screen {
+                CurrentScreen::EventLog => KeyContext::EventLog,
+                CurrentScreen::Logs => KeyContext::Logs,
+                CurrentScreen::BulkTest => KeyContext::BulkTest,
+                CurrentScreen::Suite => KeyContext::Suite,
+                _ => KeyContext::Main,
+            }
+        }
+    }
+
+    fn handle_command(&self, command: Command) -> Result<()> {
+        match command {
+            Command::Quit => self.send_quit()?,
+            Command::ForceQuit => self.action_tx.send(Action::Quit)?,
+            Command::FocusNext => self.action_tx.send(Action::CycleFocusUp)?,
+            Command::FocusPrevious => self.action_tx.send(Action::CycleFocusDown)?,
+            Command::JumpToTile(num) => self.action_tx.send(Action::JumpToTile(num))?,
+            Command::Activate => self.activate_tile(&self.current_focus)?,
+            Command::ToggleEventLog => self.action_tx.send(Action::ToggleEventLog)?,
+            Command::ToggleLogView => self.action_tx.send(Action::ToggleLogView)?,
+            Command::ToggleHelp => self.action_tx.send(Action::ToggleHelp)?,
+            Command::Close => self.action_tx.send(Action::CloseScreen)?,
+            Command::ScrollUp => self.action_tx.send(Action::ScrollUp)?,
+            Command::ScrollDown => self.action_tx.send(Action::ScrollDown)?,
+            Command::CycleLogLevel => self.action_tx.send(Action::CycleLogLevel)?,
+            Command::ToggleLogFollow => self.action_tx.send(Action::ToggleLogFollow)?,
+            Command::StartSearch => self.action_tx.send(Action::StartLogSearch)?,
+            Command::CycleSort => self.action_tx.send(Action::CycleBulkSort)?,
+            Command::ReverseSort => self.action_tx.send(Action::ReverseBulkSort)?,
+            Command::DialogAccept => self.action_tx.send(Action::DialogAccept)?,
+            Command::DialogCancel => self.action_tx.send(Action::DialogCancel)?,
+            Command::DialogToggleSelection => self.action_tx.send(Action::DialogToggleSelection)?,
+            Command::DialogSubmit => {
+                if self.dialog.as_ref().is_some_and(|d| d.yes_selected) {
+                    self.action_tx.send(Action::DialogAccept)?
                 } else {
-                    self.action_tx.send(Action::Key(*key))?
+                    self.action_tx.send(Action::DialogCancel)?
                 }
             }
-            KeyCode::Char(val) => {
-                if !self.is_currently_editing && val.is_numeric() {
-                    // subtract 48 as u8, since the char->u8 conversion converts to ascii code
-                    // so char('1') is 49 in u8
-                    self.action_tx
-                        .send(Action::JumpToTile((val as u8) - 48u8))?
+            Command::SubmitInput => self.action_tx.send(Action::SubmitInput)?,
+            Command::OpenPalette => self.action_tx.send(Action::OpenPalette)?,
+            Command::Suspend => self.action_tx.send(Action::Suspend)?,
+            Command::CancelInput => self.action_tx.send(Action::CancelInput)?,
+            Command::Noop => {}
+        }
+        Ok(())
+    }
+
+    /// Runs the operation of a tile, the same as pressing enter on the focused tile
+    fn activate_tile(&self, tile: &CurrentFocus) -> Result<()> {
+        match tile {
+            CurrentFocus::RefreshLists => self.send_with_confirmation(ConfirmDialog::new(
+                "Refresh Blocking Lists",
+                format!("Refresh the blocking lists on server {}?", self.api.url),
+                Action::RefreshLists,
+            ))?,
+            CurrentFocus::DeleteCache => self.send_with_confirmation(ConfirmDialog::new(
+                "Delete DNS Cache",
+                format!("Flush the DNS cache on server {}?", self.api.url),
+                Action::ClearDNSCache,
+            ))?,
+            CurrentFocus::BlockingStatus => self.send_toggle_blocking()?,
+            CurrentFocus::QueryDNS => self.action_tx.send(Action::StartQueryInput)?,
+            CurrentFocus::DNSStatus => self.action_tx.send(Action::UpdateDNSStatus)?,
+        }
+        Ok(())
+    }
+
+    /// Translates the selected palette entry into actions
+    pub fn send_palette_command(&self, palette_match: PaletteMatch) -> Result<()> {
+        let argument = palette_match.argument.as_str();
+        match palette_match.entry.command {
+            PaletteCommand::FlushCache => self.send_with_confirmation(ConfirmDialog::new(
+                "Delete DNS Cache",
+                format!("Flush the DNS cache on server {}?", self.api.url),
+                Action::ClearDNSCache,
+            ))?,
+            PaletteCommand::RefreshLists => self.send_with_confirmation(ConfirmDialog::new(
+                "Refresh Blocking Lists",
+                format!("Refresh the blocking lists on server {}?", self.api.url),
+                Action::RefreshLists,
+            ))?,
+            PaletteCommand::EnableBlocking => self.action_tx.send(Action::EnableDNSBlocking)?,
+            PaletteCommand::DisableBlocking => {
+                let duration = if argument.is_empty() {
+                    None
                 } else {
-                    self.action_tx.send(Action::Key(*key))?
+                    match parse_duration(argument) {
+                        Ok(duration) => Some(duration),
+                        Err(err) => {
+                            self.action_tx
+                                .send(Action::Notify(NotificationLevel::Warn, err.to_string()))?;
+                            return Ok(());
+                        }
+                    }
+                };
+                self.send_disable_blocking(duration)?
+            }
+            PaletteCommand::Query => match DNSQuery::parse(argument) {
+                Some(query) => self.action_tx.send(Action::SubmitDNSQuery(query))?,
+                None => self.action_tx.send(Action::Notify(
+                    NotificationLevel::Warn,
+                    "query needs a domain, e.g. 'query example.com'".to_string(),
+                ))?,
+            },
+            PaletteCommand::CheckHealth => self.action_tx.send(Action::UpdateDNSStatus)?,
+            PaletteCommand::EventLog => self.action_tx.send(Action::ToggleEventLog)?,
+            PaletteCommand::Logs => self.action_tx.send(Action::ToggleLogView)?,
+            PaletteCommand::EditConfig => self.action_tx.send(Action::EditFile(
+                Config::path(),
+                Box::new(Action::ReloadConfig),
+            ))?,
+            PaletteCommand::EditList if argument.is_empty() => {
+                self.action_tx.send(Action::Notify(
+                    NotificationLevel::Warn,
+                    "edit list needs a file".to_string(),
+                ))?
+            }
+            PaletteCommand::EditList => self.action_tx.send(Action::EditFile(
+                PathBuf::from(argument),
+                Box::new(Action::RefreshLists),
+            ))?,
+            PaletteCommand::BulkTest => match bulk::parse_argument(argument) {
+                Some((file, query_type)) => self
+                    .action_tx
+                    .send(Action::StartBulkTest(file, query_type))?,
+                None if self.bulk_test.is_some() => self.action_tx.send(Action::ShowBulkTest)?,
+                None => self.action_tx.send(Action::Notify(
+                    NotificationLevel::Warn,
+                    "bulk test needs a domain list, e.g. 'bulk test top-domains.txt'".to_string(),
+                ))?,
+            },
+            PaletteCommand::RunSuite if !argument.is_empty() => self
+                .action_tx
+                .send(Action::StartSuite(PathBuf::from(argument)))?,
+            PaletteCommand::RunSuite if self.suite_run.is_some() => {
+                self.action_tx.send(Action::ShowSuite)?
+            }
+            PaletteCommand::RunSuite => self.action_tx.send(Action::Notify(
+                NotificationLevel::Warn,
+                "run suite needs a suite file, e.g. 'run suite smoke.yaml'".to_string(),
+            ))?,
+            PaletteCommand::Watch => {
+                let mut parts = argument.split_whitespace();
+                let (Some(domain), outcome) = (parts.next(), parts.next()) else {
+                    self.action_tx.send(Action::Notify(
+                        NotificationLevel::Warn,
+                        "watch needs a domain, e.g. 'watch bank.example resolved'".to_string(),
+                    ))?;
+                    return Ok(());
+                };
+                match outcome.map_or(Ok(Outcome::Resolved), Outcome::parse) {
+                    Ok(outcome) => self.action_tx.send(Action::Watch(Expectation::new(
+                        &domain.to_lowercase(),
+                        outcome,
+                    )))?,
+                    Err(err) => self
+                        .action_tx
+                        .send(Action::Notify(NotificationLevel::Warn, err.to_string()))?,
                 }
             }
-            KeyCode::Tab => self.action_tx.send(Action::CycleFocusUp)?,
-            KeyCode::BackTab => self.action_tx.send(Action::CycleFocusDown)?,
-            _ => {}
+            PaletteCommand::Unwatch if argument.is_empty() => {
+                self.action_tx.send(Action::Notify(
+                    NotificationLevel::Warn,
+                    "unwatch needs a domain".to_string(),
+                ))?
+            }
+            PaletteCommand::Unwatch => self
+                .action_tx
+                .send(Action::Unwatch(argument.to_lowercase()))?,
+            PaletteCommand::ExportResults if argument.is_empty() => {
+                self.action_tx.send(Action::Notify(
+                    NotificationLevel::Warn,
+                    "export results needs a file".to_string(),
+                ))?
+            }
+            PaletteCommand::ExportResults => self
+                .action_tx
+                .send(Action::ExportBulkResults(PathBuf::from(argument)))?,
+            PaletteCommand::Help => self.action_tx.send(Action::ToggleHelp)?,
+            PaletteCommand::Quit => self.send_quit()?,
+        }
+        Ok(())
+    }
+
+    fn send_quit(&self) -> Result<()> {
+        self.send_with_confirmation(ConfirmDialog::new(
+            "Quit",
+            "Do you really want to quit Blocky TUI?",
+            Action::Quit,
+        ))
+    }
+
+    /// Enabling blocking is harmless, disabling it affects every client and has to be confirmed
+    fn send_toggle_blocking(&self) -> Result<()> {
+        match &self.blocking_status {
+            Some(status) if !status.is_blocking_enabled => {
+                self.action_tx.send(Action::EnableDNSBlocking)?
+            }
+            _ => self.send_disable_blocking(None)?,
+        }
+        Ok(())
+    }
+
+    fn send_disable_blocking(&self, duration: Option<Duration>) -> Result<()> {
+        let message = match duration {
+            Some(duration) => format!(
+                "Disable blocking for all groups on server {} for {}s?",
+                self.api.url,
+                duration.as_secs()
+            ),
+            None => format!(
+                "Disable blocking for all groups on server {}?",
+                self.api.url
+            ),
+        };
+        self.send_with_confirmation(ConfirmDialog::new(
+            "Disable Blocking",
+            message,
+            Action::DisableDNSBlocking(duration),
+        ))
+    }
+
+    /// Shows the dialog or directly sends its action if confirmations are disabled in the config
+    pub fn send_with_confirmation(&self, dialog: ConfirmDialog) -> Result<()> {
+        if self.config.confirm_destructive_actions {
+            self.action_tx.send(Action::ShowDialog(dialog))?
+        } else {
+            self.action_tx.send(*dialog.on_confirm)?
         }
         Ok(())
     }
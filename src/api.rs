@@ -1,103 +1,526 @@
-use anyhow::{anyhow, Result};
-use reqwest::Response;
-use serde::{Deserialize, Serialize};
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Certificate, ClientBuilder, Identity, RequestBuilder, Response,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use thiserror::Error;
 
+use std::collections::BTreeMap;
+use std::error::Error as _;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 use url::Url;
 
+use crate::secret::resolve_secret;
+use crate::server::{AuthConfig, ServerProfile, TlsConfig};
+use crate::transport::Transport;
+#[cfg(unix)]
+use crate::transport::{send_unix_socket, UnixSocketError};
+
+/// Errors of requests to the blocky API
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ApiError {
+    #[error("invalid API URL: {0}")]
+    InvalidUrl(String),
+    #[error("could not connect to {url}, is blocky running?")]
+    ConnectionRefused { url: String },
+    #[error("could not resolve the host of {url}: {message}")]
+    Resolve { url: String, message: String },
+    #[error("request to {url} timed out")]
+    Timeout { url: String },
+    #[error("TLS error when connecting to {url}: {message}")]
+    Tls { url: String, message: String },
+    #[error("{url} returned HTTP {status}{}", body_suffix(.body))]
+    Status {
+        url: String,
+        status: u16,
+        body: String,
+    },
+    #[error("could not decode response of {url}: {message}")]
+    Decode { url: String, message: String },
+    #[error("request to {url} failed: {message}")]
+    Request { url: String, message: String },
+    #[error("invalid server configuration: {0}")]
+    Config(String),
+}
+
+fn body_suffix(body: &str) -> String {
+    if body.is_empty() {
+        String::new()
+    } else {
+        format!(": {body}")
+    }
+}
+
+impl ApiError {
+    fn from_reqwest(err: reqwest::Error, url: &Url) -> Self {
+        let url = url.to_string();
+        // the whole chain is needed, the top level error only says "error sending request"
+        let mut message = err.to_string();
+        let mut source = err.source();
+        while let Some(cause) = source {
+            message = format!("{message}: {cause}");
+            source = cause.source();
+        }
+        let mut causes = std::iter::successors(err.source(), |&cause| cause.source());
+        if err.is_timeout() {
+            ApiError::Timeout { url }
+        } else if let Some(err) = causes
+            .clone()
+            .find_map(|cause| cause.downcast_ref::<ResolveError>())
+        {
+            ApiError::Resolve {
+                url,
+                message: err.source.to_string(),
+            }
+        } else if causes.clone().any(|cause| cause.is::<native_tls::Error>()) {
+            ApiError::Tls { url, message }
+        } else if causes.any(|cause| {
+            cause
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|err| err.kind() == std::io::ErrorKind::ConnectionRefused)
+        }) {
+            ApiError::ConnectionRefused { url }
+        } else if err.is_decode() {
+            ApiError::Decode { url, message }
+        } else {
+            ApiError::Request { url, message }
+        }
+    }
+
+    /// Process exit code for command line usage, 0 and 1 are not used
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ApiError::InvalidUrl(_) => 2,
+            ApiError::ConnectionRefused { .. } => 3,
+            ApiError::Timeout { .. } => 4,
+            ApiError::Tls { .. } => 5,
+            ApiError::Status { .. } => 6,
+            ApiError::Decode { .. } => 7,
+            ApiError::Request { .. } => 8,
+            ApiError::Config(_) => 9,
+            ApiError::Resolve { .. } => 10,
+        }
+    }
+
+    #[cfg(unix)]
+    fn from_unix_socket(err: UnixSocketError, url: &Url) -> Self {
+        let url = url.to_string();
+        match err {
+            UnixSocketError::Connect(err)
+                if matches!(
+                    err.kind(),
+                    std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::NotFound
+                ) =>
+            {
+                ApiError::ConnectionRefused { url }
+            }
+            err => ApiError::Request {
+                url,
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+pub type ApiResult<T> = std::result::Result<T, ApiError>;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Failed lookup of a host name, a type of its own so that it can be found in the error chain
+#[derive(Debug, Error)]
+#[error("could not resolve {host}")]
+struct ResolveError {
+    host: String,
+    #[source]
+    source: std::io::Error,
+}
+
+/// Resolves host names with the resolver of the system like reqwest's default resolver
+struct SystemResolver;
+
+impl Resolve for SystemResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await
+                .map_err(|source| ResolveError {
+                    host: host.clone(),
+                    source,
+                })?
+                .collect();
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ApiClient {
     /// Blocky API Base Url
     pub url: Url,
     pub dns_port: u16,
     pub api_port: u16,
+    pub transport: Transport,
+    /// default headers of the client, reqwest only adds them to the requests it sends itself
+    headers: HeaderMap,
     client: reqwest::Client,
+    credentials: Option<Credentials>,
 }
 
-#[derive(Debug, Serialize, Clone)]
+/// Resolved credentials of a server profile
+#[derive(Clone)]
+enum Credentials {
+    Basic { username: String, password: String },
+    Bearer(String),
+}
+
+impl Credentials {
+    fn resolve(auth: &AuthConfig) -> ApiResult<Self> {
+        let secret = |value: &str| {
+            resolve_secret(value).map_err(|err| ApiError::Config(format!("auth: {err:#}")))
+        };
+        Ok(match auth {
+            AuthConfig::Basic { username, password } => Credentials::Basic {
+                username: username.clone(),
+                password: secret(password)?,
+            },
+            AuthConfig::Bearer { token } => Credentials::Bearer(secret(token)?),
+        })
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credentials::Basic { username, .. } => write!(f, "Basic({username}, <redacted>)"),
+            Credentials::Bearer(_) => write!(f, "Bearer(<redacted>)"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
 pub struct DNSQuery {
-    pub query: &'static str,
+    pub query: String,
     #[serde(rename = "type")]
-    pub query_type: &'static str,
+    pub query_type: String,
 }
 
-#[allow(non_snake_case)]
-#[derive(Default, Debug, Serialize, Deserialize)]
+impl DNSQuery {
+    /// Parses user input like `example.com` or `example.com AAAA`, the query type defaults to `A`
+    pub fn parse(input: &str) -> Option<Self> {
+        let mut parts = input.split_whitespace();
+        let query = parts.next()?.to_string();
+        let query_type = parts.next().unwrap_or("A").to_uppercase();
+        Some(Self { query, query_type })
+    }
+}
+
+/// Response of blocky's query endpoint
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub struct DNSResponse {
     pub reason: String,
     pub response: String,
-    pub responseType: String,
-    pub returnCode: String,
+    /// e.g. `RESOLVED`, `CACHED` or `BLOCKED`
+    pub response_type: String,
+    /// DNS return code, e.g. `NOERROR` or `NXDOMAIN`
+    pub return_code: String,
+}
+
+/// Response of blocky's blocking status endpoint
+#[derive(Default, Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct BlockingStatusResponse {
+    pub enabled: bool,
+    #[serde(default)]
+    pub disabled_groups: Vec<String>,
+    #[serde(default)]
+    pub auto_enable_in_sec: u32,
 }
 
 impl ApiClient {
-    pub fn new(base_url: &'static str, api_port: u16, dns_port: u16) -> Result<Self> {
-        let mut url = Url::parse(base_url)?;
+    pub fn new(base_url: &str, api_port: u16, dns_port: u16) -> ApiResult<Self> {
+        Self::from_profile(&ServerProfile {
+            url: base_url.to_string(),
+            api_port: Some(api_port),
+            dns_port,
+            ..ServerProfile::default()
+        })
+    }
+
+    /// Creates the client for a server profile, resolving all of its secrets
+    pub fn from_profile(profile: &ServerProfile) -> ApiResult<Self> {
+        let url = Self::base_url(&profile.url, profile.api_port)?;
+        let api_port = url
+            .port_or_known_default()
+            .ok_or_else(|| ApiError::InvalidUrl(format!("{} has no port", profile.url)))?;
+
+        let headers = Self::headers(&profile.headers)?;
+        let mut builder = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .dns_resolver(Arc::new(SystemResolver))
+            .default_headers(headers.clone());
+        let transport = match (&profile.proxy, &profile.unix_socket) {
+            (Some(_), Some(_)) => {
+                return Err(ApiError::Config(
+                    "proxy and unix_socket can not be used together".to_string(),
+                ))
+            }
+            (Some(proxy), None) => {
+                let proxy = resolve_secret(proxy)
+                    .map_err(|err| ApiError::Config(format!("proxy: {err:#}")))?;
+                let transport = Transport::proxy(&proxy);
+                let proxy = reqwest::Proxy::all(&proxy)
+                    .map_err(|err| ApiError::Config(format!("invalid proxy: {err}")))?;
+                builder = builder.proxy(proxy);
+                transport
+            }
+            (None, Some(_)) if cfg!(not(unix)) => {
+                return Err(ApiError::Config(
+                    "Unix sockets are not supported on this platform".to_string(),
+                ))
+            }
+            (None, Some(path)) => Transport::UnixSocket(path.clone()),
+            (None, None) => Transport::Direct,
+        };
+        let client = Self::configure_tls(builder, &profile.tls, &url)?
+            .build()
+            .map_err(|err| ApiError::from_reqwest(err, &url))?;
+        let credentials = profile
+            .auth
+            .as_ref()
+            .map(Credentials::resolve)
+            .transpose()?;
+
+        let api = ApiClient {
+            url,
+            dns_port: profile.dns_port,
+            api_port,
+            transport,
+            headers,
+            client,
+            credentials,
+        };
+        debug!("created new API client: {api:?}");
+        Ok(api)
+    }
+
+    /// Parses the base URL, the path is kept so that blocky can be served below a prefix
+    fn base_url(base_url: &str, api_port: Option<u16>) -> ApiResult<Url> {
+        let mut url = Url::parse(base_url)
+            .map_err(|err| ApiError::InvalidUrl(format!("{base_url}: {err}")))?;
         match url.scheme() {
             "http" | "https" => {}
             "" => {
                 if url.set_scheme("https").is_err() {
-                    return Err(anyhow!("could not set Blocky API URL scheme to 'https'"));
+                    return Err(ApiError::InvalidUrl(
+                        "could not set Blocky API URL scheme to 'https'".to_string(),
+                    ));
                 };
             }
             _ => {
-                let err = anyhow!("Blocky API URL {} is not http or https", base_url);
+                let err = ApiError::InvalidUrl(format!("{base_url} is not http or https"));
                 error!(%err);
                 return Err(err);
             }
         }
-        url.set_port(Some(api_port)).or(Err(anyhow!(
-            "could not set API port to API URL -> is the URL valid? {base_url}"
-        )))?;
-        // reset path if any
-        url.set_path("");
-        let api = ApiClient {
-            url,
-            dns_port,
-            api_port,
-            client: reqwest::Client::builder()
-                .timeout(Duration::from_secs(10))
-                .build()
-                .unwrap(),
+        if let Some(api_port) = api_port {
+            url.set_port(Some(api_port)).map_err(|_| {
+                ApiError::InvalidUrl(format!(
+                    "could not set API port -> is the URL valid? {base_url}"
+                ))
+            })?;
+        }
+        // endpoints are joined to the base URL, which drops the last path segment without a trailing slash
+        if !url.path().ends_with('/') {
+            let path = format!("{}/", url.path());
+            url.set_path(&path);
+        }
+        url.set_query(None);
+        url.set_fragment(None);
+        Ok(url)
+    }
+
+    fn headers(headers: &BTreeMap<String, String>) -> ApiResult<HeaderMap> {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            let value = resolve_secret(value)
+                .map_err(|err| ApiError::Config(format!("header {name}: {err:#}")))?;
+            let name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|err| ApiError::Config(format!("header {name}: {err}")))?;
+            let mut value = HeaderValue::from_str(&value)
+                .map_err(|err| ApiError::Config(format!("header {name}: {err}")))?;
+            // header values often carry API keys, they must not show up in logs
+            value.set_sensitive(true);
+            map.insert(name, value);
+        }
+        Ok(map)
+    }
+
+    fn configure_tls(
+        mut builder: ClientBuilder,
+        tls: &TlsConfig,
+        url: &Url,
+    ) -> ApiResult<ClientBuilder> {
+        let tls_error = |message: String| ApiError::Tls {
+            url: url.to_string(),
+            message,
         };
-        debug!("created new API client: {api:?}");
-        Ok(api)
+        let read = |path: &Path| {
+            std::fs::read(path).map_err(|err| tls_error(format!("could not read {path:?}: {err}")))
+        };
+
+        if let Some(path) = &tls.ca_cert {
+            let certs = Certificate::from_pem_bundle(&read(path)?)
+                .map_err(|err| tls_error(format!("invalid CA certificate {path:?}: {err}")))?;
+            for cert in certs {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+        match (&tls.client_cert, &tls.client_key) {
+            (Some(cert), Some(key)) => {
+                let identity = Identity::from_pkcs8_pem(&read(cert)?, &read(key)?)
+                    .map_err(|err| tls_error(format!("invalid client certificate: {err}")))?;
+                builder = builder.identity(identity);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(ApiError::Config(
+                    "client_cert and client_key have to be set together".to_string(),
+                ))
+            }
+        }
+        if tls.insecure {
+            warn!("TLS certificate validation is disabled for {url}");
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        Ok(builder)
+    }
+
+    fn endpoint(&self, path: &str) -> ApiResult<Url> {
+        self.url
+            .join(path)
+            .map_err(|err| ApiError::InvalidUrl(format!("{}{path}: {err}", self.url)))
+    }
+
+    /// Sends the request, responses with a non-success status are returned as [`ApiError::Status`]
+    async fn send(&self, request: RequestBuilder, url: &Url) -> ApiResult<Response> {
+        let request = match &self.credentials {
+            Some(Credentials::Basic { username, password }) => {
+                request.basic_auth(username, Some(password))
+            }
+            Some(Credentials::Bearer(token)) => request.bearer_auth(token),
+            None => request,
+        };
+        let resp = match &self.transport {
+            #[cfg(unix)]
+            Transport::UnixSocket(path) => {
+                let request = request
+                    .build()
+                    .map_err(|err| ApiError::from_reqwest(err, url))?;
+                tokio::time::timeout(
+                    REQUEST_TIMEOUT,
+                    send_unix_socket(path, &self.headers, request),
+                )
+                .await
+                .map_err(|_| ApiError::Timeout {
+                    url: url.to_string(),
+                })?
+                .map_err(|err| ApiError::from_unix_socket(err, url))?
+            }
+            _ => request
+                .send()
+                .await
+                .map_err(|err| ApiError::from_reqwest(err, url))?,
+        };
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ApiError::Status {
+                url: url.to_string(),
+                status: status.as_u16(),
+                body: body.trim().to_string(),
+            });
+        }
+        Ok(resp)
+    }
+
+    async fn decode<T: DeserializeOwned>(resp: Response, url: &Url) -> ApiResult<T> {
+        let body = resp
+            .text()
+            .await
+            .map_err(|err| ApiError::from_reqwest(err, url))?;
+        serde_json::from_str(&body).map_err(|err| ApiError::Decode {
+            url: url.to_string(),
+            message: err.to_string(),
+        })
     }
 
     /// Post a a request to refresh blocky's blocking lists
-    pub async fn post_refresh_list_cmd(&self) -> Result<Response> {
+    pub async fn post_refresh_list_cmd(&self) -> ApiResult<()> {
         debug!("posting request to refresh blocking lists");
-        let url = self.url.join("api/lists/refresh")?;
-        let resp = self
-            .client
-            .post(url.to_string())
-            .header("accept", "text/plain")
-            .send()
-            .await?;
-        Ok(resp)
+        let url = self.endpoint("api/lists/refresh")?;
+        let request = self.client.post(url.clone()).header("accept", "text/plain");
+        self.send(request, &url).await?;
+        Ok(())
     }
 
     /// Post a a request to delete the DNS response cache
-    pub async fn post_clear_dns_cache(&self) -> Result<Response> {
-        debug!("posting request to refresh blocking lists");
-        let url = self.url.join("api/cache/flush")?;
-        let resp = self.client.post(url.to_string()).send().await?;
-        Ok(resp)
+    pub async fn post_clear_dns_cache(&self) -> ApiResult<()> {
+        debug!("posting request to delete the DNS cache");
+        let url = self.endpoint("api/cache/flush")?;
+        self.send(self.client.post(url.clone()), &url).await?;
+        Ok(())
     }
 
-    pub async fn post_dnsquery(&self, query: DNSQuery) -> Result<DNSResponse> {
+    /// Get the current blocking status
+    pub async fn get_blocking_status(&self) -> ApiResult<BlockingStatusResponse> {
+        debug!("requesting blocking status");
+        let url = self.endpoint("api/blocking/status")?;
+        let resp = self.send(self.client.get(url.clone()), &url).await?;
+        let status = Self::decode(resp, &url).await?;
+        debug!("received blocking status: {status:?}");
+        Ok(status)
+    }
+
+    /// Enable blocking for all groups
+    pub async fn get_enable_blocking(&self) -> ApiResult<()> {
+        debug!("requesting to enable blocking");
+        let url = self.endpoint("api/blocking/enable")?;
+        self.send(self.client.get(url.clone()), &url).await?;
+        Ok(())
+    }
+
+    /// Disable blocking for all groups, optionally only for the given duration
+    pub async fn get_disable_blocking(&self, duration: Option<Duration>) -> ApiResult<()> {
+        debug!("requesting to disable blocking for {duration:?}");
+        let mut url = self.endpoint("api/blocking/disable")?;
+        if let Some(duration) = duration {
+            url.query_pairs_mut()
+                .append_pair("duration", &format!("{}s", duration.as_secs()));
+        }
+        self.send(self.client.get(url.clone()), &url).await?;
+        Ok(())
+    }
+
+    pub async fn post_dnsquery(&self, query: DNSQuery) -> ApiResult<DNSResponse> {
         debug!("posting DNS query: {query:?}");
-        let url = self.url.join("api/query")?;
-        let resp = self
+        let url = self.endpoint("api/query")?;
+        let request = self
             .client
-            .post(url.to_string())
+            .post(url.clone())
             .header("Content-Type", "application/json")
-            .json(&query)
-            .send()
-            .await?
-            .json::<DNSResponse>()
-            .await?;
+            .json(&query);
+        let resp = self.send(request, &url).await?;
+        let resp = Self::decode(resp, &url).await?;
         debug!("received DNS response: {resp:?}");
         Ok(resp)
     }
@@ -105,9 +528,22 @@ impl ApiClient {
 
 #[cfg(test)]
 mod tests {
-    use crate::api::ApiClient;
+    use crate::api::{ApiClient, ApiError, DNSQuery};
+    use crate::server::ServerProfile;
     use anyhow::Result;
 
+    #[test]
+    fn test_dns_query_parsing() {
+        let query = DNSQuery::parse("example.com").unwrap();
+        assert_eq!(query.query, "example.com");
+        assert_eq!(query.query_type, "A");
+
+        let query = DNSQuery::parse("  example.com aaaa ").unwrap();
+        assert_eq!(query.query_type, "AAAA");
+
+        assert!(DNSQuery::parse("   ").is_none());
+    }
+
     #[test]
     fn test_domain_name_parsing() -> Result<()> {
         let api = ApiClient::new("https://dns.test.com", 4000, 53)?;
@@ -127,17 +563,29 @@ mod tests {
         let api = ApiClient::new("https://dns.test.com/api", 4000, 53)?;
         assert_eq!(
             api.url.to_string(),
-            "https://dns.test.com:4000/",
+            "https://dns.test.com:4000/api/",
             "check if URL parsing works for https://dns.test.com/api"
         );
 
-        let api = ApiClient::new("https://dns.test.com:1234/api", 4000, 53)?;
+        let api = ApiClient::new("https://dns.test.com:1234/api/", 4000, 53)?;
         assert_eq!(
             api.url.to_string(),
-            "https://dns.test.com:4000/",
-            "check if URL parsing works for https://dns.test.com:4000/api"
+            "https://dns.test.com:4000/api/",
+            "check if URL parsing works for https://dns.test.com:4000/api/"
         );
 
+        let api = ApiClient::from_profile(&ServerProfile {
+            url: "https://dns.test.com/blocky".to_string(),
+            api_port: None,
+            ..ServerProfile::default()
+        })?;
+        assert_eq!(api.url.to_string(), "https://dns.test.com/blocky/");
+        assert_eq!(api.api_port, 443);
+
+        let err = ApiClient::new("ftp://dns.test.com", 4000, 53).unwrap_err();
+        assert!(matches!(err, ApiError::InvalidUrl(_)));
+        assert_eq!(err.exit_code(), 2);
+
         Ok(())
     }
 }
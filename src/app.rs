@@ -1,14 +1,32 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
+use ratatui::{backend::Backend, layout::Rect, Terminal};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
 
 use crate::action::Action;
-use crate::api::ApiClient;
-use crate::port_check::PortState;
-use crate::tui::{self};
+use crate::api::{ApiClient, DNSQuery, DNSResponse};
+use crate::bulk::BulkTest;
+use crate::config::Config;
+use crate::dialog::ConfirmDialog;
+use crate::editor::{edit_file, editor_command};
+use crate::keymap::Keymap;
+use crate::log_view::LogView;
+use crate::notification::{NotificationLevel, Notifications};
+use crate::palette::CommandPalette;
+use crate::port_check::{AddressProbe, PortState};
+use crate::suite::SuiteRun;
+use crate::tasks::TaskManager;
+use crate::theme::Theme;
+use crate::tui::{self, Event};
+use crate::watchlist::Watchlist;
 
 #[derive(Debug)]
 pub struct App {
+    pub config: Config,
+    pub keymap: Keymap,
+    pub theme: Theme,
     pub api: ApiClient,
     pub action_tx: UnboundedSender<Action>,
     pub action_rx: UnboundedReceiver<Action>,
@@ -22,6 +40,42 @@ pub struct App {
     pub dns_status: DNSStatus,
     pub cache_delete_state: Option<ActionState>,
     pub blocking_list_refresh_state: Option<ActionState>,
+    /// toasts and event log of the current session
+    pub notifications: Notifications,
+    /// state of the in-app log viewer
+    pub log_view: LogView,
+    /// currently opened modal dialog, it receives all key events while open
+    pub dialog: Option<ConfirmDialog>,
+    /// true while the key binding help overlay is shown
+    pub show_help: bool,
+    /// the opened command palette
+    pub palette: Option<CommandPalette>,
+    /// text typed into the query tile
+    pub query_input: String,
+    pub dns_query_state: Option<DNSQueryState>,
+    /// size of the terminal, used for mouse hit-testing
+    pub terminal_area: Rect,
+    /// the state changed since the last frame was drawn
+    pub dirty: bool,
+    /// background operations in flight
+    pub tasks: TaskManager,
+    /// handed over to the main loop, which owns the terminal
+    pub terminal_request: Option<TerminalRequest>,
+    /// the running or last finished bulk test
+    pub bulk_test: Option<BulkTest>,
+    /// the running or last finished test suite
+    pub suite_run: Option<SuiteRun>,
+    /// domains which are checked on a schedule
+    pub watchlist: Watchlist,
+}
+
+/// Operations which need the terminal, the TUI is suspended while they run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TerminalRequest {
+    /// stop the process until it is resumed from the shell
+    Suspend,
+    /// open the file in the editor, then run the action
+    Edit(PathBuf, Box<Action>),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -39,6 +93,10 @@ pub struct DNSStatus {
     pub query_response_state: Option<ApiQueryResponseState>,
     pub tcp_port_state: Option<PortState>,
     pub udp_port_state: Option<PortState>,
+    /// per address results of the API port probe, empty unless the server was resolved
+    pub tcp_addresses: Vec<AddressProbe>,
+    /// per address results of the DNS probe
+    pub udp_addresses: Vec<AddressProbe>,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -51,15 +109,23 @@ pub enum ApiQueryResponseState {
     NoResponse,
 }
 
+/// State of the last DNS query sent from the query tile or the command palette
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DNSQueryState {
+    Waiting(DNSQuery),
+    Success(DNSQuery, DNSResponse),
+    Failure(DNSQuery, String),
+}
+
 /// Represents the blocking status of blocky
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub struct BlockingState {
     /// true if blocking is enabled
     pub is_blocking_enabled: bool,
     ///  If blocking is temporary disabled: amount of seconds until blocking will be enabled
     pub unblocking_timer: Option<u32>,
     /// Disabled group names
-    disabled_groups: Option<String>,
+    pub disabled_groups: Option<String>,
 }
 
 /// Store the currently focused tile.
@@ -117,13 +183,21 @@ impl CurrentFocus {
 ///
 /// Setup -> Initial Setup Dialog (TODO)
 /// Main -> Overview of all Tiles
-/// Exiting -> Confirm Exit (TODO)
+/// Exiting -> Confirm Exit
+/// EventLog -> Scrollback of all notifications of this session
+/// Logs -> Recent tracing output of the app
+/// BulkTest -> Results of querying a list of domains
+/// Suite -> Pass/fail report of a test suite
 #[derive(Debug, Default, PartialEq, Eq)]
 pub enum CurrentScreen {
     #[default]
     Main,
     Setup,
     Exiting,
+    EventLog,
+    Logs,
+    BulkTest,
+    Suite,
 }
 
 /// Stores the current app's running state.
@@ -138,10 +212,19 @@ pub enum RunningState {
 }
 
 impl App {
-    pub fn new() -> Result<Self> {
-        let api = ApiClient::new("http://localhost", 4000, 1234)?;
+    pub fn new(config: Config) -> Result<Self> {
+        let api = ApiClient::from_profile(&config.server_profile()?)?;
+        let keymap = Keymap::new(&config.keybindings)?;
+        let theme = Theme::new(
+            config
+                .theme
+                .resolve(std::env::var("NO_COLOR").ok().as_deref()),
+        );
         let (action_tx, action_rx) = unbounded_channel::<Action>();
         let app = Self {
+            config,
+            keymap,
+            theme,
             api,
             action_tx: action_tx.clone(),
             action_rx,
@@ -153,35 +236,178 @@ impl App {
             dns_status: DNSStatus::default(),
             cache_delete_state: None,
             blocking_list_refresh_state: None,
+            notifications: Notifications::default(),
+            log_view: LogView::default(),
+            dialog: None,
+            show_help: false,
+            palette: None,
+            query_input: String::new(),
+            dns_query_state: None,
+            terminal_area: Rect::default(),
+            dirty: true,
+            tasks: TaskManager::new(action_tx.clone()),
+            terminal_request: None,
+            bulk_test: None,
+            suite_run: None,
+            watchlist: Watchlist::default(),
         };
         debug!("created new app struct");
         Ok(app)
     }
+
+    /// Loads the watchlist from `path`, a broken file is reported and the watchlist stays empty
+    ///
+    /// The empty watchlist has no file, so the broken one is kept for the user to fix it.
+    pub fn with_watchlist(mut self, path: PathBuf) -> Self {
+        match Watchlist::load(path) {
+            Ok(watchlist) => self.watchlist = watchlist,
+            Err(err) => {
+                warn!("could not load the watchlist: {err:#}");
+                self.notify(
+                    NotificationLevel::Error,
+                    format!("Watchlist not loaded: {err:#}"),
+                );
+            }
+        }
+        self
+    }
+
     pub async fn run(&mut self) -> Result<()> {
-        let mut tui = tui::Tui::new()?.frame_rate(3.0);
+        let tui = tui::Tui::new()?.frame_rate(10.0).mouse(true).paste(true);
+        self.run_with(tui).await
+    }
+
+    /// Runs the main loop in `tui`, the terminal is restored however the loop ends
+    pub async fn run_with<B: Backend>(&mut self, mut tui: tui::Tui<B>) -> Result<()> {
+        let result = self.main_loop(&mut tui).await;
+        // requests still in flight are of no interest anymore
+        self.tasks.cancel_all();
+        let exited = tui.exit().await;
+        match (result, exited) {
+            // the error of the loop is more interesting, the other one would get lost otherwise
+            (Err(err), Err(exit_err)) => {
+                error!("could not restore the terminal: {exit_err:?}");
+                Err(err)
+            }
+            (result, exited) => result.and(exited),
+        }
+    }
+
+    async fn main_loop<B: Backend>(&mut self, tui: &mut tui::Tui<B>) -> Result<()> {
         tui.enter()?;
+        let size = tui.size()?;
+        self.action_tx
+            .send(Action::Resize(size.width, size.height))?;
         info!("starting main app loop");
         loop {
-            if let Some(evt) = tui.next().await {
-                self.handle_event(&evt)?;
-
-                while let Ok(action) = self.action_rx.try_recv() {
-                    self.update(&action)?;
-                    if let Action::Render = action {
-                        tui.draw(|f| {
-                            self.render(f);
-                        })?;
-                    }
-                }
-            };
+            // results of spawned tasks are applied as soon as they arrive, not on the next event
+            tokio::select! {
+                Some(evt) = tui.next() => self.handle_event(&evt)?,
+                Some(action) = self.action_rx.recv() => self.apply(&action)?,
+            }
+            self.process_actions(&mut tui.terminal)?;
+            if let Some(request) = self.terminal_request.take() {
+                self.run_terminal_request(request, tui).await?;
+            }
             if self.running_state == RunningState::Done {
                 break;
             }
         }
-        tui.exit()?;
         Ok(())
     }
 
+    /// Hands the terminal over to the shell or the editor and takes it back afterwards
+    async fn run_terminal_request<B: Backend>(
+        &mut self,
+        request: TerminalRequest,
+        tui: &mut tui::Tui<B>,
+    ) -> Result<()> {
+        match request {
+            TerminalRequest::Suspend => {
+                info!("suspending");
+                tui.suspend().await?;
+                info!("resumed");
+            }
+            TerminalRequest::Edit(path, then) => {
+                tui.exit().await?;
+                let command =
+                    editor_command(std::env::var("VISUAL").ok(), std::env::var("EDITOR").ok());
+                match edit_file(&command, &path).await {
+                    Ok(()) => self.action_tx.send(*then)?,
+                    Err(err) => self.action_tx.send(Action::Notify(
+                        NotificationLevel::Error,
+                        format!("Could not edit {}: {err:#}", path.display()),
+                    ))?,
+                }
+            }
+        }
+        tui.resume()?;
+        // the terminal may have been resized in the meantime and the server state may have changed
+        let size = tui.size()?;
+        self.action_tx
+            .send(Action::Resize(size.width, size.height))?;
+        self.action_tx.send(Action::UpdateBlockingStatus)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Applies a changed config, the server connection is kept
+    ///
+    /// Nothing is changed if the key bindings of the config are invalid.
+    pub fn reload_config(&mut self, config: Config) -> Result<()> {
+        self.keymap = Keymap::new(&config.keybindings)?;
+        self.theme = Theme::new(
+            config
+                .theme
+                .resolve(std::env::var("NO_COLOR").ok().as_deref()),
+        );
+        self.config = config;
+        Ok(())
+    }
+
+    /// Handles a single event and runs all pending actions, rendering into `terminal` if anything changed
+    ///
+    /// Independent of the terminal backend, so tests can drive the app with a `TestBackend`.
+    pub fn process_event<B: Backend>(
+        &mut self,
+        event: &Event,
+        terminal: &mut Terminal<B>,
+    ) -> Result<()> {
+        self.handle_event(event)?;
+        self.process_actions(terminal)
+    }
+
+    /// Runs all actions which are currently queued, then draws a single frame if any of them
+    /// changed the state
+    pub fn process_actions<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> Result<()> {
+        while let Ok(action) = self.action_rx.try_recv() {
+            self.apply(&action)?;
+        }
+        if self.dirty {
+            terminal.draw(|f| {
+                self.render(f);
+            })?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Updates the state with the action and marks the screen for redrawing
+    pub fn apply(&mut self, action: &Action) -> Result<()> {
+        self.update(action)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    /// Whether a render tick has to redraw although no action changed the state
+    ///
+    /// New log records, expiring toasts and progress indicators change the screen without an action.
+    pub fn needs_tick_render(&self) -> bool {
+        self.current_screen == CurrentScreen::Logs
+            || self.notifications.toasts_changing()
+            || self.tasks.running().next().is_some()
+    }
+
     pub fn change_running_state(&mut self, state: RunningState) {
         self.running_state = state
     }
@@ -195,4 +421,51 @@ impl App {
     pub fn set_tile_to_num(&mut self, num: u8) {
         self.current_focus.set_on_number(num);
     }
+
+    pub fn toggle_event_log(&mut self) {
+        self.current_screen = match self.current_screen {
+            CurrentScreen::EventLog => CurrentScreen::Main,
+            _ => {
+                self.notifications.scroll = 0;
+                CurrentScreen::EventLog
+            }
+        }
+    }
+
+    pub fn toggle_log_view(&mut self) {
+        self.current_screen = match self.current_screen {
+            CurrentScreen::Logs => CurrentScreen::Main,
+            _ => CurrentScreen::Logs,
+        }
+    }
+
+    /// Opens the dialog, a dialog confirming the quit action is shown on the exit screen
+    pub fn show_dialog(&mut self, dialog: ConfirmDialog) {
+        if *dialog.on_confirm == Action::Quit {
+            self.current_screen = CurrentScreen::Exiting;
+        }
+        self.dialog = Some(dialog);
+    }
+
+    /// Closes the dialog and returns the action which should be run if it was confirmed
+    pub fn close_dialog(&mut self) -> Option<Action> {
+        if self.current_screen == CurrentScreen::Exiting {
+            self.current_screen = CurrentScreen::Main;
+        }
+        self.dialog.take().map(|d| *d.on_confirm)
+    }
+
+    /// True while the user types into the input of the query tile
+    pub fn is_editing_query(&self) -> bool {
+        self.is_currently_editing
+            && self.palette.is_none()
+            && !self.log_view.is_searching
+            && self.current_screen == CurrentScreen::Main
+            && self.current_focus == CurrentFocus::QueryDNS
+    }
+
+    /// Push a notification from synchronous code, background tasks send `Action::Notify` instead
+    pub fn notify(&mut self, level: NotificationLevel, message: impl Into<String>) {
+        self.notifications.push(level, message.into());
+    }
 }
@@ -0,0 +1,536 @@
+//! Runs a list of domains through the query API, e.g. to check a new block list against
+//! domains which must keep resolving before it is enabled
+
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashSet},
+    fmt::Write,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use futures::{stream, Stream, StreamExt};
+use rustdns::Type;
+use serde::Serialize;
+
+use crate::api::{ApiClient, DNSQuery, DNSResponse};
+
+/// Queries in flight at the same time, unless configured otherwise
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Entries of hosts files which are not part of a block list
+const HOSTS_FILE_NAMES: &[&str] = &[
+    "localhost",
+    "localhost.localdomain",
+    "local",
+    "broadcasthost",
+    "ip6-localhost",
+    "ip6-loopback",
+    "ip6-localnet",
+    "ip6-mcastprefix",
+    "ip6-allnodes",
+    "ip6-allrouters",
+    "ip6-allhosts",
+];
+
+/// Reads domains from a list with one domain per line or from a file in hosts format
+///
+/// Comments, duplicates and the loopback names of hosts files are skipped.
+pub fn parse_domains(content: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut domains = Vec::new();
+    for line in content.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut fields = line.split_whitespace().peekable();
+        // in hosts format the address is followed by one or more names
+        if fields.peek().is_some_and(|f| f.parse::<IpAddr>().is_ok()) {
+            fields.next();
+        }
+        for field in fields {
+            let domain = field.trim_end_matches('.').to_lowercase();
+            if HOSTS_FILE_NAMES.contains(&domain.as_str()) || domain.parse::<IpAddr>().is_ok() {
+                continue;
+            }
+            if seen.insert(domain.clone()) {
+                domains.push(domain);
+            }
+        }
+    }
+    domains
+}
+
+pub fn read_domains(path: &Path) -> Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read domain list {}", path.display()))?;
+    let domains = parse_domains(&content);
+    if domains.is_empty() {
+        bail!("{} does not contain any domain", path.display());
+    }
+    Ok(domains)
+}
+
+/// Outcome of the query for a single domain of the list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkResult {
+    /// position of the domain in the list
+    pub index: usize,
+    pub domain: String,
+    /// the response of blocky or the error of the request
+    pub outcome: Result<DNSResponse, String>,
+}
+
+impl BulkResult {
+    /// e.g. `RESOLVED` or `BLOCKED`, failed requests are reported as `ERROR`
+    pub fn response_type(&self) -> &str {
+        match &self.outcome {
+            Ok(response) => &response.response_type,
+            Err(_) => "ERROR",
+        }
+    }
+
+    /// the reason given by blocky or the error message
+    pub fn reason(&self) -> &str {
+        match &self.outcome {
+            Ok(response) => &response.reason,
+            Err(err) => err,
+        }
+    }
+
+    /// the answer records, e.g. `A (192.0.2.1)`, empty for failed requests
+    pub fn response(&self) -> &str {
+        match &self.outcome {
+            Ok(response) => &response.response,
+            Err(_) => "",
+        }
+    }
+}
+
+/// Splits the argument of the `bulk test` palette command into the domain list and query type
+///
+/// The path may contain spaces, its last word is only taken for the query type if it is one.
+pub fn parse_argument(argument: &str) -> Option<(PathBuf, String)> {
+    let argument = argument.trim();
+    if argument.is_empty() {
+        return None;
+    }
+    match argument.rsplit_once(char::is_whitespace) {
+        Some((file, query_type)) if Type::from_str(&query_type.to_uppercase()).is_ok() => {
+            Some((PathBuf::from(file.trim_end()), query_type.to_uppercase()))
+        }
+        _ => Some((PathBuf::from(argument), "A".to_string())),
+    }
+}
+
+/// Queries all domains with at most `concurrency` requests in flight
+///
+/// Results are yielded in the order they arrive, not in the order of the list.
+pub fn query_all(
+    api: ApiClient,
+    domains: Vec<String>,
+    query_type: String,
+    concurrency: usize,
+) -> impl Stream<Item = BulkResult> {
+    stream::iter(domains.into_iter().enumerate())
+        .map(move |(index, domain)| {
+            let api = api.clone();
+            let query = DNSQuery {
+                query: domain.clone(),
+                query_type: query_type.clone(),
+            };
+            async move {
+                let outcome = api
+                    .post_dnsquery(query)
+                    .await
+                    .map_err(|err| err.to_string());
+                BulkResult {
+                    index,
+                    domain,
+                    outcome,
+                }
+            }
+        })
+        .buffer_unordered(concurrency.max(1))
+}
+
+/// Column the results are sorted by
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SortColumn {
+    /// order of the domain list
+    #[default]
+    Input,
+    Domain,
+    #[value(name = "type")]
+    ResponseType,
+    Reason,
+}
+
+impl SortColumn {
+    pub fn next(self) -> Self {
+        match self {
+            SortColumn::Input => SortColumn::Domain,
+            SortColumn::Domain => SortColumn::ResponseType,
+            SortColumn::ResponseType => SortColumn::Reason,
+            SortColumn::Reason => SortColumn::Input,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Input => "input order",
+            SortColumn::Domain => "domain",
+            SortColumn::ResponseType => "type",
+            SortColumn::Reason => "reason",
+        }
+    }
+}
+
+/// Order of two results by the column, ties keep the order of the domain list
+fn compare(a: &BulkResult, b: &BulkResult, column: SortColumn, descending: bool) -> Ordering {
+    let ordering = match column {
+        SortColumn::Input => a.index.cmp(&b.index),
+        SortColumn::Domain => a.domain.cmp(&b.domain),
+        SortColumn::ResponseType => a.response_type().cmp(b.response_type()),
+        SortColumn::Reason => a.reason().cmp(b.reason()),
+    };
+    let ordering = if descending {
+        ordering.reverse()
+    } else {
+        ordering
+    };
+    ordering.then(a.index.cmp(&b.index))
+}
+
+pub fn sort_results(results: &mut [BulkResult], column: SortColumn, descending: bool) {
+    results.sort_by(|a, b| compare(a, b, column, descending));
+}
+
+/// Number of results per response type
+pub fn summarize(results: &[BulkResult]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for result in results {
+        *counts
+            .entry(result.response_type().to_string())
+            .or_default() += 1;
+    }
+    counts
+}
+
+/// e.g. `3 domains: 1 BLOCKED, 2 RESOLVED`
+pub fn summary_line(results: &[BulkResult]) -> String {
+    let counts = summarize(results)
+        .into_iter()
+        .map(|(response_type, count)| format!("{count} {response_type}"))
+        .collect::<Vec<_>>();
+    format!("{} domains: {}", results.len(), counts.join(", "))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Table,
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// JSON for `.json` files, CSV for all other files
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("json") => ExportFormat::Json,
+            _ => ExportFormat::Csv,
+        }
+    }
+}
+
+/// A result as written to CSV and JSON exports
+#[derive(Debug, Serialize)]
+struct ExportRow<'a> {
+    domain: &'a str,
+    response_type: &'a str,
+    reason: &'a str,
+    response: &'a str,
+    return_code: &'a str,
+}
+
+impl<'a> From<&'a BulkResult> for ExportRow<'a> {
+    fn from(result: &'a BulkResult) -> Self {
+        let return_code = match &result.outcome {
+            Ok(response) => response.return_code.as_str(),
+            Err(_) => "",
+        };
+        Self {
+            domain: &result.domain,
+            response_type: result.response_type(),
+            reason: result.reason(),
+            response: result.response(),
+            return_code,
+        }
+    }
+}
+
+pub fn export(results: &[BulkResult], format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Table => to_table(results),
+        ExportFormat::Csv => to_csv(results),
+        ExportFormat::Json => {
+            let rows: Vec<ExportRow> = results.iter().map(ExportRow::from).collect();
+            serde_json::to_string_pretty(&rows)? + "\n"
+        }
+    })
+}
+
+/// Writes the results to `path` in the format of its extension
+pub fn export_to_file(results: &[BulkResult], path: &Path) -> Result<()> {
+    let content = export(results, ExportFormat::from_path(path))?;
+    std::fs::write(path, content).with_context(|| format!("could not write {}", path.display()))
+}
+
+fn to_csv(results: &[BulkResult]) -> String {
+    // quotes are only needed for fields with separators, quotes or line breaks
+    let field = |value: &str| {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    };
+    let mut csv = String::from("domain,response_type,reason,response,return_code\n");
+    for row in results.iter().map(ExportRow::from) {
+        let fields = [
+            row.domain,
+            row.response_type,
+            row.reason,
+            row.response,
+            row.return_code,
+        ];
+        let fields: Vec<String> = fields.into_iter().map(field).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+fn to_table(results: &[BulkResult]) -> String {
+    let width = |column: fn(&BulkResult) -> &str, header: &str| {
+        results
+            .iter()
+            .map(|r| column(r).chars().count())
+            .chain([header.len()])
+            .max()
+            .unwrap_or_default()
+    };
+    let domain_width = width(|r| &r.domain, "DOMAIN");
+    let type_width = width(BulkResult::response_type, "TYPE");
+    let mut table = format!(
+        "{:domain_width$}  {:type_width$}  REASON\n",
+        "DOMAIN", "TYPE"
+    );
+    for result in results {
+        let _ = writeln!(
+            table,
+            "{:domain_width$}  {:type_width$}  {}",
+            result.domain,
+            result.response_type(),
+            result.reason()
+        );
+    }
+    table
+}
+
+/// State of the bulk test screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BulkTest {
+    /// the domain list
+    pub source: PathBuf,
+    pub query_type: String,
+    /// number of domains in the list
+    pub total: usize,
+    /// results received so far, in the shown order
+    pub results: Vec<BulkResult>,
+    pub sort: SortColumn,
+    pub descending: bool,
+    /// index of the first shown row
+    pub scroll: usize,
+}
+
+impl BulkTest {
+    pub fn new(source: PathBuf, query_type: String, total: usize) -> Self {
+        Self {
+            source,
+            query_type,
+            total,
+            results: Vec::with_capacity(total),
+            sort: SortColumn::default(),
+            descending: false,
+            scroll: 0,
+        }
+    }
+
+    /// Inserts the result at its position in the shown order
+    pub fn push(&mut self, result: BulkResult) {
+        let position = self.results.partition_point(|other| {
+            compare(other, &result, self.sort, self.descending) == Ordering::Less
+        });
+        self.results.insert(position, result);
+    }
+
+    pub fn cycle_sort(&mut self) {
+        self.sort = self.sort.next();
+        self.sort();
+    }
+
+    pub fn reverse(&mut self) {
+        self.descending = !self.descending;
+        self.sort();
+    }
+
+    fn sort(&mut self) {
+        sort_results(&mut self.results, self.sort, self.descending);
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.results.len() >= self.total
+    }
+
+    pub fn scroll_up(&mut self, amount: usize) {
+        self.scroll = self.scroll.saturating_sub(amount);
+    }
+
+    pub fn scroll_down(&mut self, amount: usize) {
+        self.scroll = (self.scroll + amount).min(self.results.len().saturating_sub(1));
+    }
+}
+
+/// Fixtures shared with the tests of other modules
+#[cfg(test)]
+pub mod test_util {
+    use super::*;
+
+    /// A successful result with a reason and answer derived from the response type
+    pub(crate) fn result(index: usize, domain: &str, response_type: &str) -> BulkResult {
+        BulkResult {
+            index,
+            domain: domain.to_string(),
+            outcome: Ok(DNSResponse {
+                reason: format!("{response_type} (test)"),
+                response: "A (192.0.2.1)".to_string(),
+                response_type: response_type.to_string(),
+                return_code: "NOERROR".to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_util::result, *};
+
+    #[test]
+    fn test_parse_plain_lists_and_hosts_files() {
+        let content = "\
+            # top domains\n\
+            Example.com.\n\
+            example.com\n\
+            \n\
+            127.0.0.1 localhost\n\
+            0.0.0.0 ads.example.net tracker.example.net # inline comment\n\
+            ::1 ip6-localhost\n";
+        assert_eq!(
+            parse_domains(content),
+            ["example.com", "ads.example.net", "tracker.example.net"]
+        );
+    }
+
+    #[test]
+    fn test_sort_and_summarize() {
+        let mut results = vec![
+            result(0, "b.example", "RESOLVED"),
+            result(1, "a.example", "BLOCKED"),
+            BulkResult {
+                index: 2,
+                domain: "c.example".to_string(),
+                outcome: Err("connection refused".to_string()),
+            },
+        ];
+        sort_results(&mut results, SortColumn::Domain, false);
+        let domains: Vec<_> = results.iter().map(|r| r.domain.as_str()).collect();
+        assert_eq!(domains, ["a.example", "b.example", "c.example"]);
+
+        sort_results(&mut results, SortColumn::ResponseType, true);
+        assert_eq!(results[0].response_type(), "RESOLVED");
+
+        sort_results(&mut results, SortColumn::Input, false);
+        assert_eq!(results[0].index, 0);
+        assert_eq!(
+            summary_line(&results),
+            "3 domains: 1 BLOCKED, 1 ERROR, 1 RESOLVED"
+        );
+    }
+
+    #[test]
+    fn test_push_keeps_the_sort_order() {
+        let mut test = BulkTest::new("domains.txt".into(), "A".to_string(), 4);
+        test.cycle_sort();
+        test.reverse();
+        for (index, domain) in ["b.example", "d.example", "a.example", "c.example"]
+            .into_iter()
+            .enumerate()
+        {
+            test.push(result(index, domain, "RESOLVED"));
+        }
+        let domains: Vec<_> = test.results.iter().map(|r| r.domain.as_str()).collect();
+        assert_eq!(
+            domains,
+            ["d.example", "c.example", "b.example", "a.example"]
+        );
+    }
+
+    #[test]
+    fn test_parse_argument() {
+        assert_eq!(parse_argument("  "), None);
+        assert_eq!(
+            parse_argument("top domains.txt"),
+            Some(("top domains.txt".into(), "A".to_string()))
+        );
+        assert_eq!(
+            parse_argument("my lists/top domains.txt aaaa"),
+            Some(("my lists/top domains.txt".into(), "AAAA".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_export_formats() -> Result<()> {
+        let mut blocked = result(0, "ads.example", "BLOCKED");
+        if let Ok(response) = &mut blocked.outcome {
+            response.reason = "BLOCKED (ads, \"tracking\")".to_string();
+        }
+        let results = [blocked, result(1, "example.com", "RESOLVED")];
+
+        let csv = export(&results, ExportFormat::Csv)?;
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines[0], "domain,response_type,reason,response,return_code");
+        assert_eq!(
+            lines[1],
+            "ads.example,BLOCKED,\"BLOCKED (ads, \"\"tracking\"\")\",A (192.0.2.1),NOERROR"
+        );
+
+        let json: serde_json::Value = serde_json::from_str(&export(&results, ExportFormat::Json)?)?;
+        assert_eq!(json[1]["domain"], "example.com");
+        assert_eq!(json[1]["response_type"], "RESOLVED");
+
+        let table = export(&results, ExportFormat::Table)?;
+        assert!(table.starts_with("DOMAIN       TYPE      REASON\n"));
+
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out.JSON")),
+            ExportFormat::Json
+        );
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out.txt")),
+            ExportFormat::Csv
+        );
+        Ok(())
+    }
+}
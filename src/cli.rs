@@ -0,0 +1,278 @@
+//! Command line interface, the TUI is started if no subcommand is given
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use clap::{Args, Parser, Subcommand};
+use futures::StreamExt;
+use tracing::info;
+
+use crate::api::ApiClient;
+use crate::bulk::{self, BulkResult, ExportFormat, SortColumn};
+use crate::config::Config;
+use crate::server::ServerProfile;
+use crate::suite::{self, CaseResult, ReportFormat, Suite};
+
+#[derive(Debug, Parser)]
+#[command(version, about = "Terminal user interface for the blocky DNS server")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Commands {
+    /// Query all domains of a list via the API and report how blocky answers them
+    Bulk(BulkArgs),
+    /// Check the expectations of a YAML test suite and report the results as TAP or JUnit XML
+    Test(TestArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct BulkArgs {
+    /// domain list with one domain per line or in hosts format
+    pub file: PathBuf,
+    /// query type of all queries
+    #[arg(short = 't', long = "type", default_value = "A")]
+    pub query_type: String,
+    /// queries in flight at the same time [default: bulk_concurrency of the config]
+    #[arg(short, long)]
+    pub concurrency: Option<usize>,
+    /// output format [default: by the extension of --output, table for stdout]
+    #[arg(short, long, value_enum)]
+    pub format: Option<ExportFormat>,
+    /// write the results to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// column the results are sorted by
+    #[arg(short, long, value_enum, default_value_t = SortColumn::Input)]
+    pub sort: SortColumn,
+    /// sort in descending order
+    #[arg(long)]
+    pub descending: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct TestArgs {
+    /// YAML file with the expectations
+    pub file: PathBuf,
+    /// server profile to test, may be repeated [default: servers of the suite, else the default server]
+    #[arg(short, long = "server")]
+    pub servers: Vec<String>,
+    /// report format [default: junit for .xml outputs, else tap]
+    #[arg(short, long, value_enum)]
+    pub format: Option<ReportFormat>,
+    /// write the report to this file instead of stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+/// Runs the subcommand with the server profile of the config
+pub async fn run(command: Commands) -> Result<()> {
+    let config = Config::load()?;
+    match command {
+        Commands::Bulk(args) => {
+            let api = ApiClient::from_profile(&config.server_profile()?)?;
+            let concurrency = args.concurrency.unwrap_or(config.bulk_concurrency);
+            run_bulk(&args, api, concurrency).await
+        }
+        Commands::Test(args) => {
+            let suite = Suite::load(&args.file)?;
+            let servers = if args.servers.is_empty() {
+                &suite.servers
+            } else {
+                &args.servers
+            };
+            let profiles = if servers.is_empty() {
+                vec![(config.server_name(), config.server_profile()?)]
+            } else {
+                suite::profiles(&config, servers)?
+            };
+            run_suite(&args, suite, profiles, config.bulk_concurrency).await
+        }
+    }
+}
+
+/// Prints or writes the results, the summary goes to stderr to keep stdout machine-readable
+///
+/// Fails if any query failed, after the results were written.
+pub async fn run_bulk(args: &BulkArgs, api: ApiClient, concurrency: usize) -> Result<()> {
+    let domains = bulk::read_domains(&args.file)?;
+    info!("bulk testing {} domains of {:?}", domains.len(), args.file);
+    let mut results: Vec<BulkResult> =
+        bulk::query_all(api, domains, args.query_type.to_uppercase(), concurrency)
+            .collect()
+            .await;
+    bulk::sort_results(&mut results, args.sort, args.descending);
+
+    let format = match (args.format, &args.output) {
+        (Some(format), _) => format,
+        (None, Some(path)) => ExportFormat::from_path(path),
+        (None, None) => ExportFormat::Table,
+    };
+    let output = bulk::export(&results, format)?;
+    match &args.output {
+        Some(path) => std::fs::write(path, output)
+            .with_context(|| format!("could not write {}", path.display()))?,
+        None => print!("{output}"),
+    }
+    eprintln!("{}", bulk::summary_line(&results));
+
+    let failed = results.iter().filter(|r| r.outcome.is_err()).count();
+    if failed > 0 {
+        bail!("{failed} of {} queries failed", results.len());
+    }
+    Ok(())
+}
+
+/// Prints or writes the report, the summary goes to stderr
+///
+/// A server whose client can not be built is reported with an error for each of its checks.
+/// Fails if any check did not pass, after the report was written.
+pub async fn run_suite(
+    args: &TestArgs,
+    suite: Suite,
+    profiles: Vec<(String, ServerProfile)>,
+    concurrency: usize,
+) -> Result<()> {
+    info!(
+        "running test suite {:?} against {} servers",
+        args.file,
+        profiles.len()
+    );
+    let results: Vec<CaseResult> = suite::run_profiles(profiles, suite.tests, concurrency)
+        .collect()
+        .await;
+
+    let format = match (args.format, &args.output) {
+        (Some(format), _) => format,
+        (None, Some(path)) => ReportFormat::from_path(path),
+        (None, None) => ReportFormat::Tap,
+    };
+    let report = suite::report(&results, format);
+    match &args.output {
+        Some(path) => std::fs::write(path, report)
+            .with_context(|| format!("could not write {}", path.display()))?,
+        None => print!("{report}"),
+    }
+    eprintln!("{}", suite::summary_line(&results));
+
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    if failed > 0 {
+        bail!("{failed} of {} checks did not pass", results.len());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+    use crate::mock_blocky::MockBlocky;
+    use crate::server::AuthConfig;
+
+    #[test]
+    fn test_cli_definition() {
+        Cli::command().debug_assert();
+        let cli = Cli::parse_from(["blocky-tui", "bulk", "top.txt", "-t", "aaaa", "-s", "type"]);
+        let Some(Commands::Bulk(args)) = cli.command else {
+            panic!("bulk subcommand was not parsed");
+        };
+        assert_eq!(args.query_type, "aaaa");
+        assert_eq!(args.sort, SortColumn::ResponseType);
+        assert!(Cli::parse_from(["blocky-tui"]).command.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_bulk() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        mock.block("ads.example.com");
+        let dir = std::env::temp_dir().join(format!("blocky-tui-bulk-{}", mock.api_port));
+        std::fs::create_dir_all(&dir)?;
+        let file = dir.join("domains.txt");
+        std::fs::write(&file, "example.com\n0.0.0.0 ads.example.com\n")?;
+
+        let args = BulkArgs {
+            file,
+            query_type: "a".to_string(),
+            concurrency: None,
+            format: None,
+            output: Some(dir.join("results.csv")),
+            sort: SortColumn::Domain,
+            descending: false,
+        };
+        run_bulk(&args, mock.api_client(), 2).await?;
+        let csv = std::fs::read_to_string(dir.join("results.csv"))?;
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("ads.example.com,BLOCKED,BLOCKED (ads),A (0.0.0.0)"));
+        assert!(lines[2].starts_with("example.com,RESOLVED"));
+
+        mock.fail("/api/query", 500);
+        assert!(run_bulk(&args, mock.api_client(), 2).await.is_err());
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_suite() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        mock.block("doubleclick.net");
+        let dir = std::env::temp_dir().join(format!("blocky-tui-suite-{}", mock.api_port));
+        std::fs::create_dir_all(&dir)?;
+        let suite = Suite::parse(
+            "
+tests:
+  - domain: example.com
+    expect: resolved
+    answer: 192.0.2.1
+    via: both
+  - domain: doubleclick.net
+    expect: blocked
+    reason: ads
+  - domain: doubleclick.net
+    expect: blocked
+    via: dns
+",
+        )?;
+        let profiles = vec![("mock".to_string(), mock.profile())];
+        let args = TestArgs {
+            file: dir.join("smoke.yaml"),
+            servers: vec![],
+            format: None,
+            output: Some(dir.join("report.xml")),
+        };
+        run_suite(&args, suite.clone(), profiles.clone(), 2).await?;
+        let junit = std::fs::read_to_string(dir.join("report.xml"))?;
+        assert!(junit.contains("tests=\"4\" failures=\"0\" errors=\"0\""));
+
+        mock.unblock("doubleclick.net");
+        assert!(run_suite(&args, suite.clone(), profiles.clone(), 2)
+            .await
+            .is_err());
+        let junit = std::fs::read_to_string(dir.join("report.xml"))?;
+        assert!(junit.contains("tests=\"4\" failures=\"2\" errors=\"0\""));
+
+        // a server whose secret can not be resolved does not keep the others from being checked
+        let broken = ServerProfile {
+            auth: Some(AuthConfig::Bearer {
+                token: "env:BLOCKY_TUI_TEST_MISSING_TOKEN".to_string(),
+            }),
+            ..mock.profile()
+        };
+        let profiles = [profiles, vec![("broken".to_string(), broken)]].concat();
+        let args = TestArgs {
+            output: Some(dir.join("report.tap")),
+            ..args
+        };
+        assert!(run_suite(&args, suite, profiles, 2).await.is_err());
+        let tap = std::fs::read_to_string(dir.join("report.tap"))?;
+        assert!(tap.starts_with("TAP version 13\n1..8\n"), "{tap}");
+        assert!(tap.contains("\nok 1 - [mock] example.com"), "{tap}");
+        assert!(tap.contains("\nnot ok 5 - [broken] example.com"), "{tap}");
+        assert_eq!(tap.matches("severity: error").count(), 4, "{tap}");
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use crate::bulk::DEFAULT_CONCURRENCY;
+use crate::keymap::KeybindingsConfig;
+use crate::logging::PROJECT_NAME;
+use crate::server::ServerProfile;
+use crate::theme::ThemeName;
+use crate::watchlist::DEFAULT_WATCH_INTERVAL;
+
+lazy_static! {
+    pub static ref CONFIG_FOLDER: Option<PathBuf> =
+        std::env::var(format!("{}_CONFIG", PROJECT_NAME.clone()))
+            .ok()
+            .map(PathBuf::from);
+    pub static ref CONFIG_FILE: String = "config.toml".to_string();
+}
+
+pub fn get_config_dir() -> PathBuf {
+    if let Some(s) = CONFIG_FOLDER.clone() {
+        s
+    } else {
+        PathBuf::from(".").join(".config")
+    }
+}
+
+/// User configuration, read from `config.toml` in the config directory
+///
+/// Every option has a default, so the config file and all of its keys are optional.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct Config {
+    /// ask for confirmation before quitting or running destructive operations
+    pub confirm_destructive_actions: bool,
+    /// key bindings per context, they are merged with the default key bindings
+    pub keybindings: KeybindingsConfig,
+    /// color palette, `NO_COLOR` in the environment overrides it with the monochrome theme
+    pub theme: ThemeName,
+    /// connection settings of the blocky instances by name
+    pub servers: BTreeMap<String, ServerProfile>,
+    /// the server profile used on startup, may be omitted if there is at most one profile
+    pub default_server: Option<String>,
+    /// queries in flight at the same time during a bulk test
+    pub bulk_concurrency: usize,
+    /// seconds between two checks of the watchlist
+    pub watch_interval: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            confirm_destructive_actions: true,
+            keybindings: KeybindingsConfig::default(),
+            theme: ThemeName::default(),
+            servers: BTreeMap::new(),
+            default_server: None,
+            bulk_concurrency: DEFAULT_CONCURRENCY,
+            watch_interval: DEFAULT_WATCH_INTERVAL,
+        }
+    }
+}
+
+impl Config {
+    /// Loads the config file from the config directory, falls back to the defaults if it does not exist
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            info!("no config file found at {path:?}, using defaults");
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("could not read config file {path:?}"))?;
+        let config = Self::parse(&content)
+            .with_context(|| format!("could not parse config file {path:?}"))?;
+        debug!("loaded config: {config:?}");
+        Ok(config)
+    }
+
+    /// Location of the config file, it does not need to exist
+    pub fn path() -> PathBuf {
+        get_config_dir().join(CONFIG_FILE.clone())
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    /// Name of the profile picked by [`Config::server_profile`], `default` if none is configured
+    pub fn server_name(&self) -> String {
+        self.default_server
+            .clone()
+            .or_else(|| self.servers.keys().next().cloned())
+            .unwrap_or_else(|| "default".to_string())
+    }
+
+    /// The profile of the server to connect to, a local blocky if no profile is configured
+    pub fn server_profile(&self) -> Result<ServerProfile> {
+        match &self.default_server {
+            Some(name) => {
+                self.servers.get(name).cloned().ok_or_else(|| {
+                    anyhow!("default_server '{name}' is not configured in [servers]")
+                })
+            }
+            None if self.servers.len() > 1 => {
+                bail!("multiple servers are configured, select one with default_server")
+            }
+            None => Ok(self.servers.values().next().cloned().unwrap_or_default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() -> Result<()> {
+        assert_eq!(Config::parse("")?, Config::default());
+
+        let config = Config::parse("confirm_destructive_actions = false")?;
+        assert!(!config.confirm_destructive_actions);
+
+        assert!(Config::parse("confirm_destructive_actions = 1").is_err());
+
+        let config = Config::parse("theme = \"colorblind-safe\"")?;
+        assert_eq!(config.theme, ThemeName::ColorblindSafe);
+        assert!(Config::parse("theme = \"pink\"").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_server_profile_selection() -> Result<()> {
+        assert_eq!(
+            Config::default().server_profile()?,
+            ServerProfile::default()
+        );
+        assert_eq!(Config::default().server_name(), "default");
+
+        let mut config = Config::parse(
+            r#"
+            [servers.home]
+            url = "https://home.example.com/blocky/"
+            [servers.office]
+            url = "https://office.example.com"
+            "#,
+        )?;
+        assert!(config.server_profile().is_err());
+
+        config.default_server = Some("office".to_string());
+        assert_eq!(config.server_profile()?.url, "https://office.example.com");
+        assert_eq!(config.server_name(), "office");
+
+        config.default_server = Some("lab".to_string());
+        assert!(config.server_profile().is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,124 @@
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Style, Stylize},
+    text::{Line, Span},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Widget, Wrap},
+};
+
+use crate::{action::Action, theme::Theme};
+
+const YES_LABEL: &str = " [y] Yes ";
+const NO_LABEL: &str = " [n] No ";
+const BUTTON_GAP: u16 = 3;
+
+/// A modal yes/no dialog which dispatches `on_confirm` when accepted
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ConfirmDialog {
+    pub title: String,
+    pub message: String,
+    /// action which is sent when the user confirms the dialog
+    pub on_confirm: Box<Action>,
+    /// true if the "Yes" button is selected, dialogs default to "No"
+    pub yes_selected: bool,
+}
+
+impl ConfirmDialog {
+    pub fn new(title: impl Into<String>, message: impl Into<String>, on_confirm: Action) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+            on_confirm: Box::new(on_confirm),
+            yes_selected: false,
+        }
+    }
+
+    pub fn toggle_selection(&mut self) {
+        self.yes_selected = !self.yes_selected;
+    }
+
+    /// The area the dialog occupies when rendered into `r`
+    pub fn area(r: Rect) -> Rect {
+        let width = 50.min(r.width);
+        let height = 7.min(r.height);
+        Rect::new(
+            r.x + (r.width - width) / 2,
+            r.y + (r.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    /// The areas of the yes and no button when the dialog is rendered into `r`
+    pub fn button_areas(r: Rect) -> (Rect, Rect) {
+        let area = Self::area(r);
+        let yes_width = YES_LABEL.len() as u16;
+        let no_width = NO_LABEL.len() as u16;
+        // the buttons are centered in the last row inside of the border
+        let inner_width = area.width.saturating_sub(2);
+        let x = area.x + 1 + inner_width.saturating_sub(yes_width + BUTTON_GAP + no_width) / 2;
+        let y = area.bottom().saturating_sub(2);
+        (
+            Rect::new(x, y, yes_width, 1).intersection(area),
+            Rect::new(x + yes_width + BUTTON_GAP, y, no_width, 1).intersection(area),
+        )
+    }
+
+    /// The dialog as widget, styled with the given theme
+    pub fn widget<'a>(&'a self, theme: &'a Theme) -> ConfirmDialogWidget<'a> {
+        ConfirmDialogWidget {
+            dialog: self,
+            theme,
+        }
+    }
+}
+
+pub struct ConfirmDialogWidget<'a> {
+    dialog: &'a ConfirmDialog,
+    theme: &'a Theme,
+}
+
+impl ConfirmDialogWidget<'_> {
+    fn button<'a>(&self, label: &'a str, selected: bool) -> Line<'a> {
+        if selected {
+            Line::styled(label, self.theme.selected.bold())
+        } else {
+            Line::styled(label, self.theme.text)
+        }
+    }
+}
+
+impl Widget for ConfirmDialogWidget<'_> {
+    fn render(self, r: Rect, buf: &mut Buffer) {
+        let area = ConfirmDialog::area(r);
+        Clear.render(area, buf);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .style(self.theme.focus)
+            .title(Span::styled(
+                self.dialog.title.as_str(),
+                Style::default().bold(),
+            ));
+        let inner = block.inner(area);
+        block.render(area, buf);
+
+        let split_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(1), Constraint::Length(1)])
+            .split(inner);
+
+        Paragraph::new(self.dialog.message.as_str())
+            .centered()
+            .wrap(Wrap { trim: true })
+            .style(self.theme.text)
+            .render(split_layout[0], buf);
+
+        let (yes_area, no_area) = ConfirmDialog::button_areas(r);
+        self.button(YES_LABEL, self.dialog.yes_selected)
+            .render(yes_area, buf);
+        self.button(NO_LABEL, !self.dialog.yes_selected)
+            .render(no_area, buf);
+    }
+}
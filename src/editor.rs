@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use tokio::process::Command;
+use tracing::info;
+
+/// The editor command line from `$VISUAL` or `$EDITOR`, like a shell would pick it
+///
+/// The value may contain arguments, e.g. `code --wait`.
+pub fn editor_command(visual: Option<String>, editor: Option<String>) -> Vec<String> {
+    let command = [visual, editor]
+        .into_iter()
+        .flatten()
+        .find(|command| !command.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string());
+    command.split_whitespace().map(String::from).collect()
+}
+
+/// Opens the file with the editor and waits until it is closed
+///
+/// The terminal has to be restored before, the editor takes it over completely.
+pub async fn edit_file(command: &[String], path: &Path) -> Result<()> {
+    let Some((program, args)) = command.split_first() else {
+        bail!("no editor configured");
+    };
+    info!("opening {path:?} with {program}");
+    let status = Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .await
+        .with_context(|| format!("could not start editor '{program}'"))?;
+    if !status.success() {
+        bail!("editor '{program}' exited with {status}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_command() {
+        let command = |visual: Option<&str>, editor: Option<&str>| {
+            editor_command(visual.map(String::from), editor.map(String::from))
+        };
+        assert_eq!(
+            command(Some("code --wait"), Some("vim")),
+            ["code", "--wait"]
+        );
+        assert_eq!(command(Some(" "), Some("nano")), ["nano"]);
+        assert_eq!(command(None, None).len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_edit_file_reports_editor_failures() {
+        let path = Path::new("/dev/null");
+        assert!(edit_file(&["true".to_string()], path).await.is_ok());
+        assert!(edit_file(&["false".to_string()], path).await.is_err());
+        let missing = ["blocky-tui-missing-editor".to_string()];
+        assert!(edit_file(&missing, path).await.is_err());
+        assert!(edit_file(&[], path).await.is_err());
+    }
+}
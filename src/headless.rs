@@ -0,0 +1,513 @@
+//! Drives the app without a terminal for end-to-end tests
+//!
+//! Scripted [`Event`]s go through the same `handle_event` -> `update` -> `render` loop as in
+//! [`App::run`], but frames are rendered into a [`TestBackend`].
+
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+use tokio::time::timeout;
+
+use crate::{app::App, tui::Event};
+
+pub struct HeadlessDriver {
+    pub app: App,
+    pub terminal: Terminal<TestBackend>,
+}
+
+impl HeadlessDriver {
+    /// Creates the driver and sends the same init and resize events as a real terminal
+    pub fn new(app: App, width: u16, height: u16) -> Result<Self> {
+        let mut driver = Self {
+            app,
+            terminal: Terminal::new(TestBackend::new(width, height))?,
+        };
+        driver.send(Event::Resize(width, height))?;
+        driver.send(Event::Init)?;
+        Ok(driver)
+    }
+
+    pub fn send(&mut self, event: Event) -> Result<()> {
+        self.app.process_event(&event, &mut self.terminal)
+    }
+
+    /// Sends all events in order
+    pub fn script(&mut self, events: impl IntoIterator<Item = Event>) -> Result<()> {
+        events.into_iter().try_for_each(|event| self.send(event))
+    }
+
+    pub fn key(&mut self, code: KeyCode) -> Result<()> {
+        self.send(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)))
+    }
+
+    pub fn ctrl(&mut self, c: char) -> Result<()> {
+        self.send(Event::Key(KeyEvent::new(
+            KeyCode::Char(c),
+            KeyModifiers::CONTROL,
+        )))
+    }
+
+    /// Types every character of `text` as a separate key press
+    pub fn type_text(&mut self, text: &str) -> Result<()> {
+        text.chars().try_for_each(|c| self.key(KeyCode::Char(c)))
+    }
+
+    pub fn click(&mut self, column: u16, row: u16) -> Result<()> {
+        self.send(Event::Mouse(MouseEvent {
+            kind: MouseEventKind::Down(MouseButton::Left),
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }))
+    }
+
+    pub fn resize(&mut self, width: u16, height: u16) -> Result<()> {
+        self.terminal.backend_mut().resize(width, height);
+        self.send(Event::Resize(width, height))
+    }
+
+    /// A render tick, like the one sent by the TUI at its frame rate
+    pub fn tick(&mut self) -> Result<()> {
+        self.send(Event::Render)
+    }
+
+    /// Runs the actions sent by spawned tasks until `done` returns true, then renders
+    pub async fn wait_for(&mut self, done: impl Fn(&App) -> bool) -> Result<()> {
+        let waited = timeout(Duration::from_secs(10), async {
+            while !done(&self.app) {
+                let Some(action) = self.app.action_rx.recv().await else {
+                    bail!("action channel closed");
+                };
+                self.app.apply(&action)?;
+            }
+            Ok(())
+        })
+        .await;
+        match waited {
+            Ok(result) => result?,
+            Err(_) => bail!("timed out waiting for app state"),
+        }
+        self.app.process_actions(&mut self.terminal)
+    }
+
+    /// Number of frames drawn so far
+    pub fn frame_count(&mut self) -> usize {
+        self.terminal.get_frame().count()
+    }
+
+    /// The last rendered frame
+    pub fn buffer(&self) -> &Buffer {
+        self.terminal.backend().buffer()
+    }
+
+    /// The last rendered frame as text, one line per row
+    pub fn screen(&self) -> String {
+        let buffer = self.buffer();
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer.get(x, y).symbol())
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::Action,
+        app::{
+            ActionState, CurrentFocus, CurrentScreen, DNSQueryState, RunningState, TerminalRequest,
+        },
+        config::Config,
+        mock_blocky::MockBlocky,
+        notification::NotificationLevel,
+        server::{AuthConfig, ServerProfile},
+        suite::{Outcome, Verdict},
+        tasks::TaskKind,
+        tui::fake::FakeDevice,
+        ui::MainLayout,
+    };
+
+    fn driver(mock: &MockBlocky) -> HeadlessDriver {
+        let mut app = App::new(Config::default()).unwrap();
+        app.api = mock.api_client();
+        HeadlessDriver::new(app, 120, 35).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_quit_with_confirmation() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut driver = driver(&mock);
+        driver.key(KeyCode::Char('q'))?;
+        driver.tick()?;
+        assert_eq!(driver.app.current_screen, CurrentScreen::Exiting);
+        assert!(driver.screen().contains("[y] Yes"));
+
+        driver.key(KeyCode::Char('n'))?;
+        assert_eq!(driver.app.running_state, RunningState::Running);
+        driver.key(KeyCode::Char('q'))?;
+        driver.key(KeyCode::Char('y'))?;
+        assert_eq!(driver.app.running_state, RunningState::Done);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scripted_flush_cache() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut driver = driver(&mock);
+        driver.key(KeyCode::Char('4'))?;
+        assert_eq!(driver.app.current_focus, CurrentFocus::DeleteCache);
+        driver.script([
+            Event::Key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE)),
+            Event::Key(KeyEvent::new(KeyCode::Char('y'), KeyModifiers::NONE)),
+        ])?;
+        driver
+            .wait_for(|app| app.cache_delete_state == Some(ActionState::Success))
+            .await?;
+        assert!(driver.screen().contains("Successfully deleted DNS cache"));
+        assert_eq!(mock.state.lock().unwrap().cache_flushes, 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_via_palette() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        mock.block("ads.example.com");
+        let mut driver = driver(&mock);
+        driver.ctrl('p')?;
+        driver.type_text("query ads.example.com")?;
+        driver.key(KeyCode::Enter)?;
+        driver
+            .wait_for(|app| matches!(app.dns_query_state, Some(DNSQueryState::Success(..))))
+            .await?;
+        assert!(driver.screen().contains("BLOCKED"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_redraws_only_on_changes() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut driver = driver(&mock);
+        // the blocking status is requested on init
+        driver.wait_for(|app| app.blocking_status.is_some()).await?;
+        let frames = driver.frame_count();
+        driver.tick()?;
+        driver.tick()?;
+        assert_eq!(driver.frame_count(), frames);
+
+        // a single frame for all actions caused by one key press
+        driver.key(KeyCode::Char('2'))?;
+        assert_eq!(driver.frame_count(), frames + 1);
+        assert_eq!(driver.app.current_focus, CurrentFocus::BlockingStatus);
+
+        // toasts are redrawn on ticks until they expired
+        driver
+            .app
+            .notify(NotificationLevel::Info, "hello".to_string());
+        driver.tick()?;
+        assert_eq!(driver.frame_count(), frames + 2);
+        assert!(driver.screen().contains("hello"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_progress_of_pending_requests() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut driver = driver(&mock);
+        driver.wait_for(|app| app.blocking_status.is_some()).await?;
+        mock.set_latency(Duration::from_millis(300));
+
+        driver.key(KeyCode::Char('3'))?;
+        driver.key(KeyCode::Enter)?;
+        driver.key(KeyCode::Char('y'))?;
+        driver
+            .wait_for(|app| app.blocking_list_refresh_state == Some(ActionState::Waiting))
+            .await?;
+        let screen = driver.screen();
+        assert!(screen.contains("1 pending"));
+        assert!(screen.contains("Requested list update..."));
+
+        // render ticks keep the indicators moving while requests are pending
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        driver.tick()?;
+        assert_ne!(driver.screen(), screen);
+
+        driver
+            .wait_for(|app| !app.tasks.is_running(TaskKind::RefreshLists))
+            .await?;
+        assert!(!driver.screen().contains("pending"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_terminal_requests() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut driver = driver(&mock);
+        driver.ctrl('z')?;
+        assert_eq!(driver.app.terminal_request, Some(TerminalRequest::Suspend));
+
+        driver.app.terminal_request = None;
+        driver.ctrl('p')?;
+        driver.type_text("edit list /etc/blocky/denylist.txt")?;
+        driver.key(KeyCode::Enter)?;
+        assert_eq!(
+            driver.app.terminal_request,
+            Some(TerminalRequest::Edit(
+                "/etc/blocky/denylist.txt".into(),
+                Box::new(Action::RefreshLists)
+            ))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_test_via_palette() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        mock.block("ads.example.com");
+        let dir = std::env::temp_dir().join(format!("blocky-tui-bulk-screen-{}", mock.api_port));
+        std::fs::create_dir_all(&dir)?;
+        let list = dir.join("domains.txt");
+        std::fs::write(&list, "wiki.example.org\nexample.com\nads.example.com\n")?;
+
+        let mut driver = driver(&mock);
+        driver.ctrl('p')?;
+        driver.type_text(&format!("bulk test {}", list.display()))?;
+        driver.key(KeyCode::Enter)?;
+        driver
+            .wait_for(|app| app.bulk_test.as_ref().is_some_and(|t| t.is_complete()))
+            .await?;
+        assert_eq!(driver.app.current_screen, CurrentScreen::BulkTest);
+        let screen = driver.screen();
+        assert!(screen.contains("3/3 domains"));
+        assert!(screen.contains("1 BLOCKED  2 RESOLVED"));
+
+        driver.key(KeyCode::Char('s'))?;
+        let results = &driver.app.bulk_test.as_ref().unwrap().results;
+        assert_eq!(results[0].domain, "ads.example.com");
+
+        let export = dir.join("results.json");
+        driver.ctrl('p')?;
+        driver.type_text(&format!("export results {}", export.display()))?;
+        driver.key(KeyCode::Enter)?;
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&export)?)?;
+        assert_eq!(json[0]["domain"], "ads.example.com");
+        assert_eq!(json[0]["response_type"], "BLOCKED");
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bulk_test_with_a_missing_list() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut driver = driver(&mock);
+        driver.app.update(&Action::StartBulkTest(
+            "missing-domains.txt".into(),
+            "A".into(),
+        ))?;
+        driver
+            .wait_for(|app| !app.tasks.is_running(TaskKind::BulkTest))
+            .await?;
+        let notification = driver.app.notifications.history().last().unwrap();
+        assert_eq!(notification.level, NotificationLevel::Error);
+        assert!(notification.message.contains("missing-domains.txt"));
+        assert!(driver.app.bulk_test.is_none());
+        assert_eq!(driver.app.current_screen, CurrentScreen::Main);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_suite_via_palette() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        mock.block("doubleclick.net");
+        let dir = std::env::temp_dir().join(format!("blocky-tui-suite-screen-{}", mock.api_port));
+        std::fs::create_dir_all(&dir)?;
+        let file = dir.join("smoke.yaml");
+        std::fs::write(
+            &file,
+            "tests:\n  - domain: example.com\n    expect: resolved\n    via: both\n  \
+             - domain: doubleclick.net\n    expect: resolved\n",
+        )?;
+
+        let mut driver = driver(&mock);
+        driver.ctrl('p')?;
+        driver.type_text(&format!("run suite {}", file.display()))?;
+        driver.key(KeyCode::Enter)?;
+        driver
+            .wait_for(|app| app.suite_run.as_ref().is_some_and(|r| r.is_complete()))
+            .await?;
+        assert_eq!(driver.app.current_screen, CurrentScreen::Suite);
+        let screen = driver.screen();
+        assert!(screen.contains("3/3 checks"));
+        assert!(screen.contains("2 passed  1 failed  0 errors"));
+        assert!(screen.contains("expected RESOLVED, got BLOCKED"));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_suite_with_a_missing_file() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut driver = driver(&mock);
+        driver
+            .app
+            .update(&Action::StartSuite("missing-suite.yaml".into()))?;
+        driver
+            .wait_for(|app| !app.tasks.is_running(TaskKind::Suite))
+            .await?;
+        let notification = driver.app.notifications.history().last().unwrap();
+        assert_eq!(notification.level, NotificationLevel::Error);
+        assert!(notification.message.contains("missing-suite.yaml"));
+        assert!(driver.app.suite_run.is_none());
+        assert_eq!(driver.app.current_screen, CurrentScreen::Main);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_suite_with_an_unusable_server_profile() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let dir = std::env::temp_dir().join(format!("blocky-tui-suite-servers-{}", mock.api_port));
+        std::fs::create_dir_all(&dir)?;
+        let file = dir.join("smoke.yaml");
+        std::fs::write(
+            &file,
+            "servers: [home, office]\ntests:\n  - domain: example.com\n    expect: resolved\n",
+        )?;
+
+        let mut driver = driver(&mock);
+        let home = ServerProfile {
+            url: "http://127.0.0.1".to_string(),
+            api_port: Some(mock.api_port),
+            dns_port: mock.dns_port,
+            ..ServerProfile::default()
+        };
+        // the secret is resolved by the task, the failure is reported for the checks of the server
+        let office = ServerProfile {
+            auth: Some(AuthConfig::Bearer {
+                token: "env:BLOCKY_TUI_TEST_MISSING_TOKEN".to_string(),
+            }),
+            ..home.clone()
+        };
+        driver.app.config.servers =
+            [("home".to_string(), home), ("office".to_string(), office)].into();
+        driver.app.update(&Action::StartSuite(file))?;
+        driver
+            .wait_for(|app| app.suite_run.as_ref().is_some_and(|r| r.is_complete()))
+            .await?;
+        let screen = driver.screen();
+        assert!(screen.contains("2/2 checks"));
+        assert!(screen.contains("1 passed  0 failed  1 errors"));
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_broken_watchlist_does_not_prevent_the_start() -> Result<()> {
+        let path = std::env::temp_dir().join(format!(
+            "blocky-tui-broken-watchlist-{}.yaml",
+            std::process::id()
+        ));
+        std::fs::write(&path, "- domain: [bank.example\n")?;
+        let app = App::new(Config::default())?.with_watchlist(path.clone());
+        assert!(app.watchlist.entries.is_empty());
+        let notification = app.notifications.history().last().unwrap();
+        assert_eq!(notification.level, NotificationLevel::Error);
+        assert!(notification.message.starts_with("Watchlist not loaded"));
+        // the file is not replaced by the empty watchlist
+        app.watchlist.save()?;
+        assert_eq!(std::fs::read_to_string(&path)?, "- domain: [bank.example\n");
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_watchlist_flip_is_notified() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut driver = driver(&mock);
+        driver.ctrl('p')?;
+        driver.type_text("watch bank.example")?;
+        driver.key(KeyCode::Enter)?;
+        let bank = driver.app.watchlist.expectations()[0].clone();
+        assert_eq!(bank.expect, Some(Outcome::Resolved));
+
+        // the schedule is driven by render ticks
+        driver.tick()?;
+        driver
+            .wait_for(|app| app.watchlist.entries[0].verdict == Some(Verdict::Pass))
+            .await?;
+        assert!(driver.screen().contains("● bank.example"));
+
+        mock.block("bank.example");
+        driver.ctrl('p')?;
+        driver.type_text("refresh lists")?;
+        driver.key(KeyCode::Enter)?;
+        driver.key(KeyCode::Char('y'))?;
+        driver
+            .wait_for(|app| app.blocking_list_refresh_state == Some(ActionState::Success))
+            .await?;
+        driver.tick()?;
+        driver
+            .wait_for(|app| matches!(app.watchlist.entries[0].verdict, Some(Verdict::Fail(_))))
+            .await?;
+        assert!(driver.screen().contains("✖ bank.example"));
+        let flip = driver.app.notifications.history().last().unwrap();
+        assert_eq!(flip.level, NotificationLevel::Error);
+        assert!(flip.message.contains("got BLOCKED (BLOCKED (ads))"));
+
+        driver.ctrl('p')?;
+        driver.type_text("unwatch bank.example")?;
+        driver.key(KeyCode::Enter)?;
+        assert!(driver.app.watchlist.entries.is_empty());
+        assert!(!driver.screen().contains("✖ bank.example"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_restores_the_terminal_on_quit() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        mock.set_latency(Duration::from_secs(60));
+        let mut app = App::new(Config::default())?;
+        app.api = mock.api_client();
+        let device = FakeDevice::default();
+        // requests of the app, e.g. the blocking status on init, must not outlive it
+        app.action_tx.send(Action::Quit)?;
+        timeout(Duration::from_secs(5), app.run_with(device.tui())).await??;
+        assert_eq!(device.calls(), ["enter", "leave"]);
+        assert_eq!(app.tasks.running().count(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_run_quits_when_the_input_is_closed() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut app = App::new(Config::default())?;
+        app.api = mock.api_client();
+        let device = FakeDevice {
+            closed_input: true,
+            ..Default::default()
+        };
+        timeout(Duration::from_secs(5), app.run_with(device.tui())).await??;
+        assert_eq!(device.calls(), ["enter", "leave"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_resize_and_click() -> Result<()> {
+        let mock = MockBlocky::start().await?;
+        let mut driver = driver(&mock);
+        driver.resize(80, 24)?;
+        driver.tick()?;
+        assert_eq!(driver.buffer().area.width, 80);
+
+        // tile areas are recalculated for the compact layout
+        let query = MainLayout::new(driver.app.terminal_area).query;
+        driver.click(query.x + 1, query.y + 1)?;
+        assert_eq!(driver.app.current_focus, CurrentFocus::QueryDNS);
+        Ok(())
+    }
+}
@@ -0,0 +1,576 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+/// Set of bindings which is active, depending on the shown screen and whether the user is typing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyContext {
+    /// the tile overview
+    Main,
+    EventLog,
+    Logs,
+    BulkTest,
+    Suite,
+    /// a modal confirmation dialog is open
+    Dialog,
+    /// the help overlay is open
+    Help,
+    /// a text input is active, unbound keys are forwarded to the input
+    Editing,
+    /// the command palette is open, unbound keys are forwarded to its input
+    Palette,
+}
+
+impl KeyContext {
+    pub fn label(&self) -> &'static str {
+        match self {
+            KeyContext::Main => "Main",
+            KeyContext::EventLog => "Event Log",
+            KeyContext::Logs => "Logs",
+            KeyContext::BulkTest => "Bulk Test",
+            KeyContext::Suite => "Test Suite",
+            KeyContext::Dialog => "Dialog",
+            KeyContext::Help => "Help",
+            KeyContext::Editing => "Text Input",
+            KeyContext::Palette => "Command Palette",
+        }
+    }
+}
+
+/// A key with its modifiers, e.g. `ctrl-c` or `shift-tab`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // the case of a character already encodes shift, terminals differ in also reporting the modifier
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        Self { code, modifiers }
+    }
+}
+
+impl From<&KeyEvent> for KeyChord {
+    fn from(key: &KeyEvent) -> Self {
+        Self::new(key.code, key.modifiers)
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // a trailing '-' is the minus key itself, e.g. "ctrl--"
+        let (modifier_part, key) = match s.strip_suffix('-') {
+            Some(rest) if rest.is_empty() || rest.ends_with('-') => (rest, "-"),
+            _ => match s.rsplit_once('-') {
+                Some((modifiers, key)) => (modifiers, key),
+                None => ("", s),
+            },
+        };
+
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in modifier_part.split('-').filter(|m| !m.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => bail!("unknown key modifier '{modifier}' in '{s}'"),
+            };
+        }
+
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => {
+                if modifiers.contains(KeyModifiers::SHIFT) {
+                    KeyCode::Char(c.to_ascii_uppercase())
+                } else {
+                    KeyCode::Char(c)
+                }
+            }
+            _ => match key.to_lowercase().as_str() {
+                "esc" | "escape" => KeyCode::Esc,
+                "enter" | "return" => KeyCode::Enter,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "space" => KeyCode::Char(' '),
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                f if f.starts_with('f') => {
+                    let num = f[1..]
+                        .parse::<u8>()
+                        .map_err(|_| anyhow!("unknown key '{key}' in '{s}'"))?;
+                    KeyCode::F(num)
+                }
+                _ => bail!("unknown key '{key}' in '{s}'"),
+            },
+        };
+        Ok(KeyChord::new(code, modifiers))
+    }
+}
+
+impl Display for KeyChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "shift-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::BackTab => write!(f, "shift-tab"),
+            KeyCode::Backspace => write!(f, "backspace"),
+            KeyCode::Delete => write!(f, "delete"),
+            KeyCode::Up => write!(f, "up"),
+            KeyCode::Down => write!(f, "down"),
+            KeyCode::Left => write!(f, "left"),
+            KeyCode::Right => write!(f, "right"),
+            KeyCode::PageUp => write!(f, "pageup"),
+            KeyCode::PageDown => write!(f, "pagedown"),
+            KeyCode::Home => write!(f, "home"),
+            KeyCode::End => write!(f, "end"),
+            KeyCode::F(num) => write!(f, "f{num}"),
+            other => write!(f, "{other:?}"),
+        }
+    }
+}
+
+/// Everything a key can be bound to
+///
+/// Commands are resolved into `Action`s by the app, since some of them depend on the current state,
+/// e.g. `activate` depends on the focused tile.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    /// quit, asks for confirmation if enabled
+    Quit,
+    /// quit without confirmation
+    ForceQuit,
+    FocusNext,
+    FocusPrevious,
+    JumpToTile(u8),
+    /// run the operation of the focused tile
+    Activate,
+    ToggleEventLog,
+    ToggleLogView,
+    ToggleHelp,
+    /// go back to the main screen
+    Close,
+    ScrollUp,
+    ScrollDown,
+    CycleLogLevel,
+    ToggleLogFollow,
+    StartSearch,
+    /// sort the bulk test results by the next column
+    CycleSort,
+    ReverseSort,
+    DialogAccept,
+    DialogCancel,
+    DialogToggleSelection,
+    /// confirm or cancel, depending on the selected button
+    DialogSubmit,
+    SubmitInput,
+    CancelInput,
+    OpenPalette,
+    /// stop the process and return to the shell, `fg` resumes it
+    Suspend,
+    /// removes a default binding
+    Noop,
+}
+
+impl Command {
+    pub fn description(&self) -> String {
+        match self {
+            Command::Quit => "quit".to_string(),
+            Command::ForceQuit => "quit without confirmation".to_string(),
+            Command::FocusNext => "focus next tile".to_string(),
+            Command::FocusPrevious => "focus previous tile".to_string(),
+            Command::JumpToTile(num) => format!("focus tile {num}"),
+            Command::Activate => "run action of focused tile".to_string(),
+            Command::ToggleEventLog => "toggle event log".to_string(),
+            Command::ToggleLogView => "toggle log viewer".to_string(),
+            Command::ToggleHelp => "toggle this help".to_string(),
+            Command::Close => "close".to_string(),
+            Command::ScrollUp => "scroll up".to_string(),
+            Command::ScrollDown => "scroll down".to_string(),
+            Command::CycleLogLevel => "cycle minimum log level".to_string(),
+            Command::ToggleLogFollow => "toggle follow mode".to_string(),
+            Command::StartSearch => "search".to_string(),
+            Command::CycleSort => "sort by next column".to_string(),
+            Command::ReverseSort => "reverse sort order".to_string(),
+            Command::DialogAccept => "confirm".to_string(),
+            Command::DialogCancel => "cancel".to_string(),
+            Command::DialogToggleSelection => "switch button".to_string(),
+            Command::DialogSubmit => "press selected button".to_string(),
+            Command::SubmitInput => "submit input".to_string(),
+            Command::CancelInput => "cancel input".to_string(),
+            Command::OpenPalette => "open command palette".to_string(),
+            Command::Suspend => "suspend to the shell".to_string(),
+            Command::Noop => "nothing".to_string(),
+        }
+    }
+}
+
+impl FromStr for Command {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(num) = s.strip_prefix("jump_to_tile_") {
+            return Ok(Command::JumpToTile(num.parse().with_context(|| {
+                format!("invalid tile number in command '{s}'")
+            })?));
+        }
+        let command = match s {
+            "quit" => Command::Quit,
+            "force_quit" => Command::ForceQuit,
+            "focus_next" => Command::FocusNext,
+            "focus_previous" => Command::FocusPrevious,
+            "activate" => Command::Activate,
+            "toggle_event_log" => Command::ToggleEventLog,
+            "toggle_log_view" => Command::ToggleLogView,
+            "toggle_help" => Command::ToggleHelp,
+            "close" => Command::Close,
+            "scroll_up" => Command::ScrollUp,
+            "scroll_down" => Command::ScrollDown,
+            "cycle_log_level" => Command::CycleLogLevel,
+            "toggle_log_follow" => Command::ToggleLogFollow,
+            "start_search" => Command::StartSearch,
+            "cycle_sort" => Command::CycleSort,
+            "reverse_sort" => Command::ReverseSort,
+            "dialog_accept" => Command::DialogAccept,
+            "dialog_cancel" => Command::DialogCancel,
+            "dialog_toggle_selection" => Command::DialogToggleSelection,
+            "dialog_submit" => Command::DialogSubmit,
+            "submit_input" => Command::SubmitInput,
+            "cancel_input" => Command::CancelInput,
+            "open_palette" => Command::OpenPalette,
+            "suspend" => Command::Suspend,
+            "noop" => Command::Noop,
+            _ => bail!("unknown command '{s}'"),
+        };
+        Ok(command)
+    }
+}
+
+/// User defined bindings as written in the config file: context -> key chord -> command name
+pub type KeybindingsConfig = HashMap<KeyContext, HashMap<String, String>>;
+
+/// Maps key chords to commands, separately for every [`KeyContext`]
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: HashMap<KeyContext, HashMap<KeyChord, Command>>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let defaults: &[(KeyContext, &[(&str, &str)])] = &[
+            (
+                KeyContext::Main,
+                &[
+                    ("esc", "quit"),
+                    ("q", "quit"),
+                    ("ctrl-c", "force_quit"),
+                    ("ctrl-z", "suspend"),
+                    ("enter", "activate"),
+                    ("tab", "focus_next"),
+                    ("shift-tab", "focus_previous"),
+                    ("1", "jump_to_tile_1"),
+                    ("2", "jump_to_tile_2"),
+                    ("3", "jump_to_tile_3"),
+                    ("4", "jump_to_tile_4"),
+                    ("5", "jump_to_tile_5"),
+                    ("e", "toggle_event_log"),
+                    ("l", "toggle_log_view"),
+                    ("?", "toggle_help"),
+                    (":", "open_palette"),
+                    ("ctrl-p", "open_palette"),
+                ],
+            ),
+            (
+                KeyContext::EventLog,
+                &[
+                    ("esc", "close"),
+                    ("q", "close"),
+                    ("e", "toggle_event_log"),
+                    ("ctrl-c", "force_quit"),
+                    ("ctrl-z", "suspend"),
+                    ("up", "scroll_up"),
+                    ("k", "scroll_up"),
+                    ("down", "scroll_down"),
+                    ("j", "scroll_down"),
+                    ("?", "toggle_help"),
+                ],
+            ),
+            (
+                KeyContext::Logs,
+                &[
+                    ("esc", "close"),
+                    ("q", "close"),
+                    ("l", "toggle_log_view"),
+                    ("ctrl-c", "force_quit"),
+                    ("ctrl-z", "suspend"),
+                    ("f", "cycle_log_level"),
+                    ("F", "toggle_log_follow"),
+                    ("/", "start_search"),
+                    ("up", "scroll_up"),
+                    ("k", "scroll_up"),
+                    ("down", "scroll_down"),
+                    ("j", "scroll_down"),
+                    ("?", "toggle_help"),
+                ],
+            ),
+            (
+                KeyContext::BulkTest,
+                &[
+                    ("esc", "close"),
+                    ("q", "close"),
+                    ("ctrl-c", "force_quit"),
+                    ("ctrl-z", "suspend"),
+                    ("s", "cycle_sort"),
+                    ("r", "reverse_sort"),
+                    ("up", "scroll_up"),
+                    ("k", "scroll_up"),
+                    ("down", "scroll_down"),
+                    ("j", "scroll_down"),
+                    (":", "open_palette"),
+                    ("ctrl-p", "open_palette"),
+                    ("?", "toggle_help"),
+                ],
+            ),
+            (
+                KeyContext::Suite,
+                &[
+                    ("esc", "close"),
+                    ("q", "close"),
+                    ("ctrl-c", "force_quit"),
+                    ("ctrl-z", "suspend"),
+                    ("up", "scroll_up"),
+                    ("k", "scroll_up"),
+                    ("down", "scroll_down"),
+                    ("j", "scroll_down"),
+                    (":", "open_palette"),
+                    ("ctrl-p", "open_palette"),
+                    ("?", "toggle_help"),
+                ],
+            ),
+            (
+                KeyContext::Dialog,
+                &[
+                    ("enter", "dialog_submit"),
+                    ("y", "dialog_accept"),
+                    ("n", "dialog_cancel"),
+                    ("esc", "dialog_cancel"),
+                    ("q", "dialog_cancel"),
+                    ("ctrl-c", "force_quit"),
+                    ("left", "dialog_toggle_selection"),
+                    ("right", "dialog_toggle_selection"),
+                    ("tab", "dialog_toggle_selection"),
+                    ("shift-tab", "dialog_toggle_selection"),
+                    ("h", "dialog_toggle_selection"),
+                    ("l", "dialog_toggle_selection"),
+                ],
+            ),
+            (
+                KeyContext::Help,
+                &[
+                    ("esc", "toggle_help"),
+                    ("q", "toggle_help"),
+                    ("?", "toggle_help"),
+                    ("ctrl-c", "force_quit"),
+                ],
+            ),
+            (
+                KeyContext::Editing,
+                &[
+                    ("enter", "submit_input"),
+                    ("esc", "cancel_input"),
+                    ("ctrl-c", "force_quit"),
+                ],
+            ),
+            (
+                KeyContext::Palette,
+                &[
+                    ("enter", "submit_input"),
+                    ("esc", "cancel_input"),
+                    ("ctrl-c", "force_quit"),
+                    ("up", "scroll_up"),
+                    ("ctrl-p", "scroll_up"),
+                    ("down", "scroll_down"),
+                    ("ctrl-n", "scroll_down"),
+                    ("tab", "scroll_down"),
+                ],
+            ),
+        ];
+
+        let bindings = defaults
+            .iter()
+            .map(|(context, bindings)| {
+                let bindings = bindings
+                    .iter()
+                    .map(|(key, command)| {
+                        (
+                            key.parse().expect("default key chord is valid"),
+                            command.parse().expect("default command is valid"),
+                        )
+                    })
+                    .collect();
+                (*context, bindings)
+            })
+            .collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Creates the default keymap and applies the user bindings on top of it
+    ///
+    /// A binding to `noop` removes the default binding of that key.
+    pub fn new(user_bindings: &KeybindingsConfig) -> Result<Self> {
+        let mut keymap = Self::default();
+        for (context, bindings) in user_bindings {
+            let context_bindings = keymap.bindings.entry(*context).or_default();
+            for (key, command) in bindings {
+                let chord = key
+                    .parse::<KeyChord>()
+                    .with_context(|| format!("invalid key binding in [{context:?}]"))?;
+                let command = command
+                    .parse::<Command>()
+                    .with_context(|| format!("invalid key binding '{key}' in [{context:?}]"))?;
+                if command == Command::Noop {
+                    context_bindings.remove(&chord);
+                } else {
+                    context_bindings.insert(chord, command);
+                }
+            }
+        }
+        Ok(keymap)
+    }
+
+    pub fn get(&self, context: KeyContext, key: &KeyEvent) -> Option<Command> {
+        self.bindings
+            .get(&context)
+            .and_then(|bindings| bindings.get(&KeyChord::from(key)))
+            .copied()
+    }
+
+    /// All bindings of a context, grouped by command and sorted by the command description
+    pub fn bindings_for(&self, context: KeyContext) -> Vec<(Vec<KeyChord>, Command)> {
+        let mut grouped: Vec<(Vec<KeyChord>, Command)> = vec![];
+        if let Some(bindings) = self.bindings.get(&context) {
+            for (chord, command) in bindings {
+                match grouped.iter_mut().find(|(_, c)| c == command) {
+                    Some((chords, _)) => chords.push(*chord),
+                    None => grouped.push((vec![*chord], *command)),
+                }
+            }
+        }
+        for (chords, _) in grouped.iter_mut() {
+            chords.sort_by_key(|c| c.to_string());
+        }
+        grouped.sort_by_key(|(_, command)| command.description());
+        grouped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_key_chords() -> Result<()> {
+        assert_eq!(
+            "ctrl-c".parse::<KeyChord>()?,
+            KeyChord::new(KeyCode::Char('c'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(
+            "shift-tab".parse::<KeyChord>()?,
+            KeyChord::new(KeyCode::BackTab, KeyModifiers::NONE)
+        );
+        assert_eq!(
+            "F".parse::<KeyChord>()?,
+            KeyChord::new(KeyCode::Char('F'), KeyModifiers::SHIFT)
+        );
+        assert_eq!(
+            "ctrl--".parse::<KeyChord>()?,
+            KeyChord::new(KeyCode::Char('-'), KeyModifiers::CONTROL)
+        );
+        assert_eq!("f5".parse::<KeyChord>()?.code, KeyCode::F(5));
+        assert!("hyper-x".parse::<KeyChord>().is_err());
+        assert!("nokey".parse::<KeyChord>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_chord_display_roundtrip() -> Result<()> {
+        for key in ["ctrl-c", "esc", "shift-tab", "F", "?", "space", "alt-enter"] {
+            assert_eq!(key.parse::<KeyChord>()?.to_string(), key);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_bindings_override_defaults() -> Result<()> {
+        let user_bindings: KeybindingsConfig = toml::from_str(
+            r#"
+            [main]
+            esc = "noop"
+            j = "focus_next"
+            [editing]
+            ctrl-g = "cancel_input"
+            "#,
+        )?;
+        let keymap = Keymap::new(&user_bindings)?;
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+        assert_eq!(keymap.get(KeyContext::Main, &key(KeyCode::Esc)), None);
+        assert_eq!(
+            keymap.get(KeyContext::Main, &key(KeyCode::Char('j'))),
+            Some(Command::FocusNext)
+        );
+        assert_eq!(
+            keymap.get(KeyContext::Main, &key(KeyCode::Char('q'))),
+            Some(Command::Quit)
+        );
+        assert_eq!(
+            keymap.get(
+                KeyContext::Editing,
+                &KeyEvent::new(KeyCode::Char('g'), KeyModifiers::CONTROL)
+            ),
+            Some(Command::CancelInput)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_user_bindings() {
+        let user_bindings: KeybindingsConfig =
+            toml::from_str("[main]\nq = \"launch_rockets\"").unwrap();
+        assert!(Keymap::new(&user_bindings).is_err());
+    }
+}
@@ -0,0 +1,140 @@
+use tracing::Level;
+
+use crate::logging::{LogBuffer, LogRecord};
+
+/// State of the in-app log viewer
+#[derive(Debug)]
+pub struct LogView {
+    /// minimum level of records which are shown
+    pub min_level: Level,
+    /// case-insensitive substring filter, empty shows everything
+    pub search: String,
+    /// true while the user types into the search field
+    pub is_searching: bool,
+    /// follow mode keeps the view at the newest records,
+    /// otherwise the view is frozen at the record id stored in `frozen_at`
+    pub follow: bool,
+    frozen_at: Option<u64>,
+    /// scroll offset counted from the newest shown record
+    pub scroll: usize,
+}
+
+impl Default for LogView {
+    fn default() -> Self {
+        Self {
+            min_level: Level::DEBUG,
+            search: String::new(),
+            is_searching: false,
+            follow: true,
+            frozen_at: None,
+            scroll: 0,
+        }
+    }
+}
+
+impl LogView {
+    /// Cycles the level filter from the most verbose to the least verbose level
+    pub fn cycle_level(&mut self) {
+        self.min_level = match self.min_level {
+            Level::TRACE => Level::DEBUG,
+            Level::DEBUG => Level::INFO,
+            Level::INFO => Level::WARN,
+            Level::WARN => Level::ERROR,
+            Level::ERROR => Level::DEBUG,
+        };
+        self.scroll = 0;
+    }
+
+    pub fn set_follow(&mut self, follow: bool, buffer: &LogBuffer) {
+        self.follow = follow;
+        if follow {
+            self.frozen_at = None;
+            self.scroll = 0;
+        } else {
+            self.frozen_at = buffer.last_id();
+        }
+    }
+
+    pub fn scroll_up(&mut self, amount: usize, buffer: &LogBuffer) {
+        if self.follow {
+            self.set_follow(false, buffer);
+        }
+        let max = self.visible_records(buffer).len().saturating_sub(1);
+        self.scroll = (self.scroll + amount).min(max);
+    }
+
+    /// Scrolling down past the newest record switches back to follow mode
+    pub fn scroll_down(&mut self, amount: usize, buffer: &LogBuffer) {
+        if self.scroll <= amount {
+            self.set_follow(true, buffer);
+        } else {
+            self.scroll -= amount;
+        }
+    }
+
+    fn matches(&self, record: &LogRecord) -> bool {
+        // tracing orders levels by verbosity, so TRACE is the "greatest" level
+        if record.level > self.min_level {
+            return false;
+        }
+        if let Some(frozen_at) = self.frozen_at {
+            if record.id > frozen_at {
+                return false;
+            }
+        }
+        self.search.is_empty()
+            || record
+                .message
+                .to_lowercase()
+                .contains(&self.search.to_lowercase())
+    }
+
+    /// All records passing the current filters, oldest first
+    pub fn visible_records(&self, buffer: &LogBuffer) -> Vec<LogRecord> {
+        buffer.filtered(|r| self.matches(r))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn buffer() -> LogBuffer {
+        let buffer = LogBuffer::new(100);
+        buffer.push(Level::DEBUG, "connecting to api".to_string());
+        buffer.push(Level::WARN, "API timed out".to_string());
+        buffer.push(Level::ERROR, "udp probe failed".to_string());
+        buffer
+    }
+
+    #[test]
+    fn test_level_and_search_filter() {
+        let buffer = buffer();
+        let mut view = LogView::default();
+        assert_eq!(view.visible_records(&buffer).len(), 3);
+
+        view.cycle_level();
+        view.cycle_level();
+        assert_eq!(view.min_level, Level::WARN);
+        assert_eq!(view.visible_records(&buffer).len(), 2);
+
+        view.search = "api".to_string();
+        let records = view.visible_records(&buffer);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].message, "API timed out");
+    }
+
+    #[test]
+    fn test_scrolling_freezes_and_resumes_follow() {
+        let buffer = buffer();
+        let mut view = LogView::default();
+        view.scroll_up(1, &buffer);
+        assert!(!view.follow);
+        buffer.push(Level::INFO, "new record".to_string());
+        assert_eq!(view.visible_records(&buffer).len(), 3);
+
+        view.scroll_down(1, &buffer);
+        assert!(view.follow);
+        assert_eq!(view.visible_records(&buffer).len(), 4);
+    }
+}
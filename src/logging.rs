@@ -1,10 +1,19 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::{self, LineWriter, Write as _};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use anyhow::Result;
 use lazy_static::lazy_static;
-use tracing::debug;
+use tracing::field::{Field, Visit};
+use tracing::{debug, Event, Level, Subscriber};
 use tracing_error::ErrorLayer;
-use tracing_subscriber::{self, layer::SubscriberExt, util::SubscriberInitExt, Layer};
+use tracing_subscriber::{
+    self, filter::Targets, layer::Context, layer::SubscriberExt, util::SubscriberInitExt, Layer,
+};
 
 lazy_static! {
     pub static ref PROJECT_NAME: String = env!("CARGO_CRATE_NAME").to_uppercase().to_string();
@@ -14,6 +23,169 @@ lazy_static! {
             .map(PathBuf::from);
     pub static ref LOG_ENV: String = format!("{}_LOGLEVEL", PROJECT_NAME.clone());
     pub static ref LOG_FILE: String = format!("{}.log", env!("CARGO_PKG_NAME"));
+    /// recent log records shown in the in-app log viewer
+    pub static ref LOG_BUFFER: LogBuffer = LogBuffer::new(LOG_BUFFER_CAPACITY);
+}
+
+/// Writer of the log file, kept to flush it before the process exits
+static LOG_WRITER: OnceLock<LogFile> = OnceLock::new();
+
+/// Shared handle to the log file, so it can be flushed from outside of the subscriber
+#[derive(Clone)]
+struct LogFile(Arc<Mutex<LineWriter<File>>>);
+
+impl LogFile {
+    fn lock(&self) -> MutexGuard<'_, LineWriter<File>> {
+        // a panic while logging must not keep the remaining records from being written
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl io::Write for LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.lock().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+}
+
+/// Amount of log records kept in memory for the in-app log viewer
+pub const LOG_BUFFER_CAPACITY: usize = 2000;
+
+/// A single formatted tracing event
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    /// monotonically increasing sequence number, used to freeze the log view
+    pub id: u64,
+    pub level: Level,
+    pub timestamp: SystemTime,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+struct LogBufferInner {
+    records: VecDeque<LogRecord>,
+    next_id: u64,
+}
+
+/// Thread-safe ring buffer of the most recent log records
+#[derive(Debug, Clone)]
+pub struct LogBuffer {
+    inner: Arc<Mutex<LogBufferInner>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(LogBufferInner::default())),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, level: Level, message: String) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        if inner.records.len() >= self.capacity {
+            inner.records.pop_front();
+        }
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.records.push_back(LogRecord {
+            id,
+            level,
+            timestamp: SystemTime::now(),
+            message,
+        });
+    }
+
+    /// Returns a copy of all records matching `filter`, oldest first
+    pub fn filtered(&self, filter: impl Fn(&LogRecord) -> bool) -> Vec<LogRecord> {
+        match self.inner.lock() {
+            Ok(inner) => inner
+                .records
+                .iter()
+                .filter(|r| filter(r))
+                .cloned()
+                .collect(),
+            Err(_) => vec![],
+        }
+    }
+
+    /// Sequence number of the newest record
+    pub fn last_id(&self) -> Option<u64> {
+        self.inner
+            .lock()
+            .ok()
+            .and_then(|inner| inner.records.back().map(|r| r.id))
+    }
+}
+
+/// Tracing layer which copies every event into a [`LogBuffer`]
+pub struct LogBufferLayer {
+    buffer: LogBuffer,
+}
+
+impl LogBufferLayer {
+    pub fn new(buffer: LogBuffer) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for LogBufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        self.buffer.push(*event.metadata().level(), visitor.message);
+    }
+}
+
+/// Collects the message and all other fields of an event into one line
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let fields = std::mem::take(&mut self.message);
+            let _ = write!(self.message, "{value:?}");
+            if !fields.is_empty() {
+                let _ = write!(self.message, "{fields}");
+            }
+        } else {
+            let _ = write!(self.message, " {}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.record_debug(field, &format_args!("{value}"));
+        } else {
+            let _ = write!(self.message, " {}={value}", field.name());
+        }
+    }
+}
+
+/// Formats a wall clock time as `HH:MM:SS` in UTC, matching the timestamps in the log file
+pub fn utc_time_label(timestamp: SystemTime) -> String {
+    let secs = timestamp
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        % 86400;
+    format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        (secs / 60) % 60,
+        secs % 60
+    )
 }
 
 pub fn get_data_dir() -> PathBuf {
@@ -28,7 +200,10 @@ pub fn initialize_logging() -> Result<()> {
     let directory = get_data_dir();
     std::fs::create_dir_all(directory.clone())?;
     let log_path = directory.join(LOG_FILE.clone());
-    let log_file = std::fs::File::create(log_path)?;
+    let log_file = LogFile(Arc::new(Mutex::new(LineWriter::new(File::create(
+        log_path,
+    )?))));
+    LOG_WRITER.get_or_init(|| log_file.clone());
     std::env::set_var(
         "RUST_LOG",
         std::env::var("RUST_LOG")
@@ -38,14 +213,60 @@ pub fn initialize_logging() -> Result<()> {
     let file_subscriber = tracing_subscriber::fmt::layer()
         .with_file(true)
         .with_line_number(true)
-        .with_writer(log_file)
+        .with_writer(move || log_file.clone())
         .with_target(false)
         .with_ansi(false)
         .with_filter(tracing_subscriber::filter::EnvFilter::from_default_env());
+    // the in-app viewer always captures debug records of this crate, filtering happens in the UI
+    let buffer_subscriber = LogBufferLayer::new(LOG_BUFFER.clone())
+        .with_filter(Targets::new().with_target(env!("CARGO_CRATE_NAME"), Level::DEBUG));
     tracing_subscriber::registry()
         .with(file_subscriber)
+        .with(buffer_subscriber)
         .with(ErrorLayer::default())
         .init();
     debug!("initialized logging");
     Ok(())
 }
+
+/// Writes out records which are still buffered, e.g. on shutdown or in the panic hook
+pub fn flush_logs() {
+    if let Some(log_file) = LOG_WRITER.get() {
+        if let Err(err) = log_file.lock().flush() {
+            eprintln!("could not flush the log file: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_buffer_drops_oldest_records() {
+        let buffer = LogBuffer::new(2);
+        buffer.push(Level::INFO, "first".to_string());
+        buffer.push(Level::WARN, "second".to_string());
+        buffer.push(Level::ERROR, "third".to_string());
+        let messages: Vec<_> = buffer
+            .filtered(|_| true)
+            .into_iter()
+            .map(|r| r.message)
+            .collect();
+        assert_eq!(messages, vec!["second", "third"]);
+        assert_eq!(buffer.last_id(), Some(2));
+    }
+
+    #[test]
+    fn test_layer_records_message_and_fields() {
+        let buffer = LogBuffer::new(10);
+        let subscriber = tracing_subscriber::registry().with(LogBufferLayer::new(buffer.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::warn!(port = 53, "probe failed");
+        });
+        let records = buffer.filtered(|_| true);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, Level::WARN);
+        assert_eq!(records[0].message, "probe failed port=53");
+    }
+}
@@ -1,48 +1,93 @@
 pub mod action;
 pub mod api;
 pub mod app;
+pub mod bulk;
+pub mod cli;
+pub mod config;
+pub mod dialog;
+pub mod editor;
+#[cfg(test)]
+mod headless;
+#[cfg(test)]
+mod integration_tests;
+pub mod keymap;
+pub mod log_view;
 pub mod logging;
+#[cfg(test)]
+mod mock_blocky;
+pub mod notification;
+pub mod palette;
+pub mod paste;
 pub mod port_check;
+pub mod secret;
+pub mod server;
+pub mod suite;
+pub mod tasks;
+pub mod theme;
+pub mod transport;
 pub mod tui;
 pub mod ui;
 pub mod update;
+pub mod watchlist;
 
 use std::panic;
 
 use anyhow::Result;
+use clap::Parser;
 use human_panic::{handle_dump, print_msg, Metadata};
 use tracing::{debug, error, info};
 
+use self::api::ApiError;
 use self::app::App;
-use self::logging::initialize_logging;
+use self::cli::Cli;
+use self::config::Config;
+use self::logging::{flush_logs, initialize_logging};
+use self::watchlist::Watchlist;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // parsed first, so `--help` and usage errors do not touch the log file
+    let cli = Cli::parse();
     initialize_logging()?;
     info!("----------- STARTING BLOCKY TUI -----------");
 
     initialize_panic_handler()?;
 
-    let mut app = App::new()?;
-    info!("initialization done");
-    let result = app.run().await;
-    if let Err(r) = result {
+    let result = match cli.command {
+        Some(command) => cli::run(command).await,
+        None => run().await,
+    };
+    if let Err(r) = &result {
         error!("Main App Error: {}", r.to_string());
+    }
+    // `process::exit` below does not run destructors
+    flush_logs();
+    if let Err(r) = result {
+        // API errors have dedicated exit codes, so scripts can tell them apart
+        if let Some(err) = r.downcast_ref::<ApiError>() {
+            eprintln!("Error: {err}");
+            std::process::exit(err.exit_code());
+        }
         return Err(r);
     }
     Ok(())
 }
 
+async fn run() -> Result<()> {
+    let config = Config::load()?;
+    let mut app = App::new(config)?.with_watchlist(Watchlist::path());
+    info!("initialization done");
+    app.run().await
+}
+
 fn initialize_panic_handler() -> Result<()> {
     let panic_hook = panic::take_hook();
     panic::set_hook(Box::new(move |panic_info| {
-        match crate::tui::Tui::new() {
-            Ok(mut t) => {
-                if let Err(r) = t.exit() {
-                    error!("Unable to reset terminal to default state {:?}", r);
-                }
+        // the hook may run outside of the runtime, so the terminal is restored synchronously
+        if crossterm::terminal::is_raw_mode_enabled().unwrap_or(true) {
+            if let Err(r) = crate::tui::restore_terminal(true, true) {
+                error!("Unable to reset terminal to default state {:?}", r);
             }
-            Err(r) => error!("Unable to exit terminal: {r:?}"),
         }
         let msg = format!(
             "{:?} at {:?}",
@@ -59,6 +104,7 @@ fn initialize_panic_handler() -> Result<()> {
         let file_path = handle_dump(&meta, panic_info);
         print_msg(file_path, &meta).expect("human-panic: printing error message to console failed");
         error!("Paniced Error: {}", msg);
+        flush_logs();
         panic_hook(panic_info)
     }));
     debug!("initialized panic handler hook");
@@ -0,0 +1,428 @@
+//! An in-process fake blocky server for tests
+//!
+//! Serves the HTTP API endpoints used by the TUI and answers DNS queries on UDP,
+//! both on ephemeral ports of 127.0.0.1. Failures and latency can be scripted per test.
+//! The API also accepts absolute request targets, so it can stand in for an HTTP proxy.
+
+use std::{
+    collections::HashMap,
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use rustdns::{Message, Type};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+    net::{TcpListener, UdpSocket},
+};
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::{
+    api::{ApiClient, BlockingStatusResponse, DNSResponse},
+    palette::parse_duration,
+    server::ServerProfile,
+};
+
+/// Scriptable state of the fake server
+#[derive(Debug, Default)]
+pub struct MockState {
+    pub blocking_enabled: bool,
+    pub disabled_groups: Vec<String>,
+    pub auto_enable_in_sec: u32,
+    /// domains which are answered with a `BLOCKED` response
+    pub blocked_domains: Vec<String>,
+    /// status codes returned instead of the regular response, by path
+    pub failures: HashMap<String, u16>,
+    /// delay before every HTTP response
+    pub latency: Duration,
+    /// the DNS responder drops all queries if false
+    pub dns_responding: bool,
+    /// all received HTTP requests as `METHOD /path?query`
+    pub requests: Vec<String>,
+    /// headers of the last HTTP request, with lowercase names
+    pub last_headers: HashMap<String, String>,
+    /// the API is served below this path, like behind a reverse proxy
+    pub path_prefix: String,
+    pub dns_queries: usize,
+    pub cache_flushes: usize,
+    pub list_refreshes: usize,
+}
+
+/// A running fake blocky, it is shut down when dropped
+pub struct MockBlocky {
+    pub api_port: u16,
+    pub dns_port: u16,
+    pub state: Arc<Mutex<MockState>>,
+    cancellation_token: CancellationToken,
+}
+
+impl MockBlocky {
+    pub async fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let dns_socket = UdpSocket::bind("127.0.0.1:0").await?;
+        let mock = Self {
+            api_port: listener.local_addr()?.port(),
+            dns_port: dns_socket.local_addr()?.port(),
+            state: Arc::new(Mutex::new(MockState {
+                blocking_enabled: true,
+                dns_responding: true,
+                ..MockState::default()
+            })),
+            cancellation_token: CancellationToken::new(),
+        };
+        tokio::spawn(serve_http(
+            listener,
+            mock.state.clone(),
+            mock.cancellation_token.clone(),
+        ));
+        tokio::spawn(serve_dns(
+            dns_socket,
+            mock.state.clone(),
+            mock.cancellation_token.clone(),
+        ));
+        Ok(mock)
+    }
+
+    /// A server profile pointing at this server
+    pub fn profile(&self) -> ServerProfile {
+        ServerProfile {
+            url: "http://127.0.0.1".to_string(),
+            api_port: Some(self.api_port),
+            dns_port: self.dns_port,
+            ..ServerProfile::default()
+        }
+    }
+
+    /// An API client pointing at this server
+    pub fn api_client(&self) -> ApiClient {
+        ApiClient::from_profile(&self.profile()).unwrap()
+    }
+
+    /// Answer all requests to `path` with the given status code
+    pub fn fail(&self, path: &str, status: u16) {
+        self.state
+            .lock()
+            .unwrap()
+            .failures
+            .insert(path.to_string(), status);
+    }
+
+    pub fn set_latency(&self, latency: Duration) {
+        self.state.lock().unwrap().latency = latency;
+    }
+
+    pub fn set_dns_responding(&self, responding: bool) {
+        self.state.lock().unwrap().dns_responding = responding;
+    }
+
+    pub fn block(&self, domain: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .blocked_domains
+            .push(domain.to_string());
+    }
+
+    pub fn unblock(&self, domain: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .blocked_domains
+            .retain(|d| d != domain);
+    }
+
+    pub fn requests(&self) -> Vec<String> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Additionally serve the API on a Unix domain socket
+    #[cfg(unix)]
+    pub fn serve_unix(&self, path: &std::path::Path) -> Result<()> {
+        let listener = tokio::net::UnixListener::bind(path)?;
+        let state = self.state.clone();
+        let cancellation_token = self.cancellation_token.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => break,
+                    accepted = listener.accept() => {
+                        let Ok((stream, _)) = accepted else {
+                            continue;
+                        };
+                        let state = state.clone();
+                        tokio::spawn(async move {
+                            if let Err(err) = handle_http_connection(stream, state).await {
+                                eprintln!("mock blocky: could not handle request: {err:?}");
+                            }
+                        });
+                    }
+                }
+            }
+        });
+        Ok(())
+    }
+}
+
+impl Drop for MockBlocky {
+    fn drop(&mut self) {
+        self.cancellation_token.cancel();
+    }
+}
+
+async fn serve_http(
+    listener: TcpListener,
+    state: Arc<Mutex<MockState>>,
+    cancellation_token: CancellationToken,
+) {
+    loop {
+        tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            accepted = listener.accept() => {
+                let Ok((stream, _)) = accepted else {
+                    continue;
+                };
+                let state = state.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = handle_http_connection(stream, state).await {
+                        eprintln!("mock blocky: could not handle request: {err:?}");
+                    }
+                });
+            }
+        }
+    }
+}
+
+/// Handles a single HTTP/1.1 request, the connection is closed afterwards
+async fn handle_http_connection(
+    mut stream: impl AsyncRead + AsyncWrite + Unpin,
+    state: Arc<Mutex<MockState>>,
+) -> Result<()> {
+    let mut reader = BufReader::new(&mut stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().context("missing method")?.to_string();
+    let target = parts.next().context("missing request target")?.to_string();
+
+    let mut content_length = 0;
+    let mut headers = HashMap::new();
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).await?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse()?;
+            }
+            headers.insert(name.to_lowercase(), value.trim().to_string());
+        }
+    }
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+
+    state.lock().unwrap().last_headers = headers;
+    let latency = state.lock().unwrap().latency;
+    tokio::time::sleep(latency).await;
+
+    let (status, content_type, body) = respond(&mut state.lock().unwrap(), &method, &target, &body);
+    let response = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reason_phrase(status),
+        body.len(),
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+/// Returns status code, content type and body of the response to a request
+fn respond(
+    state: &mut MockState,
+    method: &str,
+    target: &str,
+    body: &[u8],
+) -> (u16, &'static str, String) {
+    state.requests.push(format!("{method} {target}"));
+    // proxies receive the absolute URL instead of only the path
+    let url = match target.strip_prefix('/') {
+        Some(_) => Url::parse(&format!("http://mock{target}")),
+        None => Url::parse(target),
+    };
+    let Ok(url) = url else {
+        return (400, "text/plain", "invalid request target".to_string());
+    };
+    let Some(path) = url.path().strip_prefix(&state.path_prefix) else {
+        return (404, "text/plain", "404 page not found".to_string());
+    };
+    if let Some(status) = state.failures.get(path) {
+        return (*status, "text/plain", "scripted failure".to_string());
+    }
+    let query = |key: &str| {
+        url.query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.to_string())
+    };
+
+    match (method, path) {
+        ("POST", "/api/query") => {
+            let Ok(request) = serde_json::from_slice::<serde_json::Value>(body) else {
+                return (400, "text/plain", "invalid query".to_string());
+            };
+            let domain = request["query"].as_str().unwrap_or_default();
+            let query_type = request["type"].as_str().unwrap_or("A");
+            let response = if state.blocked_domains.iter().any(|d| d == domain) {
+                DNSResponse {
+                    reason: "BLOCKED (ads)".to_string(),
+                    response: format!("{query_type} (0.0.0.0)"),
+                    response_type: "BLOCKED".to_string(),
+                    return_code: "NOERROR".to_string(),
+                }
+            } else {
+                DNSResponse {
+                    reason: "RESOLVED (mock)".to_string(),
+                    response: format!("{query_type} (192.0.2.1)"),
+                    response_type: "RESOLVED".to_string(),
+                    return_code: "NOERROR".to_string(),
+                }
+            };
+            json(&response)
+        }
+        ("POST", "/api/lists/refresh") => {
+            state.list_refreshes += 1;
+            (200, "text/plain", String::new())
+        }
+        ("POST", "/api/cache/flush") => {
+            state.cache_flushes += 1;
+            (200, "text/plain", String::new())
+        }
+        ("GET", "/api/blocking/enable") => {
+            state.blocking_enabled = true;
+            state.disabled_groups.clear();
+            state.auto_enable_in_sec = 0;
+            (200, "text/plain", String::new())
+        }
+        ("GET", "/api/blocking/disable") => {
+            let duration = match query("duration").map(|d| parse_duration(&d)) {
+                Some(Ok(duration)) => duration,
+                Some(Err(err)) => return (400, "text/plain", err.to_string()),
+                None => Duration::ZERO,
+            };
+            state.blocking_enabled = false;
+            state.auto_enable_in_sec = duration.as_secs() as u32;
+            state.disabled_groups = match query("groups") {
+                Some(groups) => groups.split(',').map(String::from).collect(),
+                None => vec!["default".to_string()],
+            };
+            (200, "text/plain", String::new())
+        }
+        ("GET", "/api/blocking/status") => json(&BlockingStatusResponse {
+            enabled: state.blocking_enabled,
+            disabled_groups: state.disabled_groups.clone(),
+            auto_enable_in_sec: state.auto_enable_in_sec,
+        }),
+        ("GET", "/metrics") => (
+            200,
+            "text/plain; version=0.0.4",
+            format!(
+                "# TYPE blocky_blocking_enabled gauge\nblocky_blocking_enabled {}\n\
+                 # TYPE blocky_query_total counter\nblocky_query_total {}\n",
+                u8::from(state.blocking_enabled),
+                state.dns_queries,
+            ),
+        ),
+        _ => (404, "text/plain", "404 page not found".to_string()),
+    }
+}
+
+fn json(value: &impl serde::Serialize) -> (u16, &'static str, String) {
+    (
+        200,
+        "application/json",
+        serde_json::to_string(value).unwrap(),
+    )
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+/// Answers DNS queries with a response to the same question
+///
+/// A queries are answered like blocky with its default `zeroIp` block type does, other queries
+/// get an empty answer.
+async fn serve_dns(
+    socket: UdpSocket,
+    state: Arc<Mutex<MockState>>,
+    cancellation_token: CancellationToken,
+) {
+    let mut buf = [0; 4096];
+    loop {
+        let (len, peer): (usize, SocketAddr) = tokio::select! {
+            _ = cancellation_token.cancelled() => break,
+            received = socket.recv_from(&mut buf) => match received {
+                Ok(received) => received,
+                Err(_) => continue,
+            },
+        };
+        // shorter messages do not even contain a DNS header
+        if len < 12 {
+            continue;
+        }
+        {
+            let mut state = state.lock().unwrap();
+            state.dns_queries += 1;
+            if !state.dns_responding {
+                continue;
+            }
+        }
+        let mut response = buf[..len].to_vec();
+        // set the QR (response) and RA (recursion available) flags, RCODE stays NOERROR
+        response[2] |= 0x80;
+        response[3] |= 0x80;
+        if let Some(ip) = a_record(&buf[..len], &state.lock().unwrap()) {
+            append_answer(&mut response, ip);
+        }
+        let _ = socket.send_to(&response, peer).await;
+    }
+}
+
+/// Address of the answer to an A query, the zero address for blocked domains
+fn a_record(query: &[u8], state: &MockState) -> Option<Ipv4Addr> {
+    let question = Message::from_slice(query)
+        .ok()?
+        .questions
+        .into_iter()
+        .next()?;
+    if question.r#type != Type::A {
+        return None;
+    }
+    let domain = question.name.trim_end_matches('.');
+    if state.blocked_domains.iter().any(|d| d == domain) {
+        Some(Ipv4Addr::UNSPECIFIED)
+    } else {
+        Some(Ipv4Addr::new(192, 0, 2, 1))
+    }
+}
+
+/// Appends an A record to a response which consists of the header and the question only
+fn append_answer(response: &mut Vec<u8>, ip: Ipv4Addr) {
+    // ANCOUNT = 1
+    response[6..8].copy_from_slice(&1u16.to_be_bytes());
+    // the name is a pointer to the question at offset 12, then type A, class IN and a TTL of 60s
+    response.extend_from_slice(&[0xc0, 0x0c, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+    response.extend_from_slice(&ip.octets());
+}
@@ -0,0 +1,147 @@
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::logging::utc_time_label;
+
+/// How long a notification stays visible as a toast in the corner overlay
+pub const TOAST_DURATION: Duration = Duration::from_secs(5);
+/// Maximum amount of toasts shown at the same time, older ones are only kept in the history
+pub const MAX_VISIBLE_TOASTS: usize = 3;
+/// Time after the expiry of a toast in which it is still redrawn, longer than a render tick
+const TOAST_EXPIRY_GRACE: Duration = Duration::from_secs(1);
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NotificationLevel {
+    Info,
+    Warn,
+    Error,
+}
+
+impl NotificationLevel {
+    pub fn label(&self) -> &'static str {
+        match self {
+            NotificationLevel::Info => "INFO",
+            NotificationLevel::Warn => "WARN",
+            NotificationLevel::Error => "ERROR",
+        }
+    }
+}
+
+/// A single message pushed by any part of the app, e.g. a finished background task
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub level: NotificationLevel,
+    pub message: String,
+    /// used for expiring the toast
+    pub created: Instant,
+    /// used for displaying the time in the event log
+    pub timestamp: SystemTime,
+}
+
+impl Notification {
+    pub fn new(level: NotificationLevel, message: String) -> Self {
+        Self {
+            level,
+            message,
+            created: Instant::now(),
+            timestamp: SystemTime::now(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.created.elapsed() >= TOAST_DURATION
+    }
+
+    pub fn time_label(&self) -> String {
+        utc_time_label(self.timestamp)
+    }
+}
+
+/// Keeps every notification of the current session
+#[derive(Debug, Default)]
+pub struct Notifications {
+    history: Vec<Notification>,
+    /// scroll offset of the event log pane, counted from the newest entry
+    pub scroll: usize,
+}
+
+impl Notifications {
+    pub fn push(&mut self, level: NotificationLevel, message: String) {
+        self.history.push(Notification::new(level, message));
+    }
+
+    /// All notifications, oldest first
+    pub fn history(&self) -> &[Notification] {
+        &self.history
+    }
+
+    /// The most recent notifications which did not expire yet, newest first
+    pub fn active_toasts(&self) -> impl Iterator<Item = &Notification> {
+        self.history
+            .iter()
+            .rev()
+            .take_while(|n| !n.is_expired())
+            .take(MAX_VISIBLE_TOASTS)
+    }
+
+    /// Whether a toast is shown or was hidden just now, so the screen has to be redrawn
+    pub fn toasts_changing(&self) -> bool {
+        // the newest notification is the last one to expire
+        self.history
+            .last()
+            .is_some_and(|n| n.created.elapsed() < TOAST_DURATION + TOAST_EXPIRY_GRACE)
+    }
+
+    pub fn scroll_up(&mut self, amount: usize) {
+        let max = self.history.len().saturating_sub(1);
+        self.scroll = (self.scroll + amount).min(max);
+    }
+
+    pub fn scroll_down(&mut self, amount: usize) {
+        self.scroll = self.scroll.saturating_sub(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_active_toasts_are_newest_first_and_capped() {
+        let mut notifications = Notifications::default();
+        for i in 0..5 {
+            notifications.push(NotificationLevel::Info, format!("msg {i}"));
+        }
+        let toasts: Vec<_> = notifications
+            .active_toasts()
+            .map(|n| n.message.as_str())
+            .collect();
+        assert_eq!(toasts, vec!["msg 4", "msg 3", "msg 2"]);
+        assert_eq!(notifications.history().len(), 5);
+    }
+
+    #[test]
+    fn test_expired_toasts_are_hidden() {
+        let mut notifications = Notifications::default();
+        notifications.push(NotificationLevel::Error, "old".to_string());
+        notifications.history[0].created -= TOAST_DURATION;
+        assert_eq!(notifications.active_toasts().count(), 0);
+        assert_eq!(notifications.history().len(), 1);
+        // redrawn once more to remove the toast from the screen
+        assert!(notifications.toasts_changing());
+        notifications.history[0].created -= TOAST_EXPIRY_GRACE;
+        assert!(!notifications.toasts_changing());
+    }
+
+    #[test]
+    fn test_scroll_is_bounded() {
+        let mut notifications = Notifications::default();
+        notifications.scroll_up(3);
+        assert_eq!(notifications.scroll, 0);
+        notifications.push(NotificationLevel::Warn, "a".to_string());
+        notifications.push(NotificationLevel::Warn, "b".to_string());
+        notifications.scroll_up(10);
+        assert_eq!(notifications.scroll, 1);
+        notifications.scroll_down(10);
+        assert_eq!(notifications.scroll, 0);
+    }
+}
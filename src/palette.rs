@@ -0,0 +1,330 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+
+/// Maximum amount of matches shown below the palette input
+pub const MAX_PALETTE_MATCHES: usize = 8;
+
+/// Operations which can be run from the command palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteCommand {
+    FlushCache,
+    RefreshLists,
+    EnableBlocking,
+    DisableBlocking,
+    Query,
+    CheckHealth,
+    EventLog,
+    Logs,
+    EditConfig,
+    EditList,
+    BulkTest,
+    ExportResults,
+    RunSuite,
+    Watch,
+    Unwatch,
+    Help,
+    Quit,
+}
+
+/// A command with its name as typed in the palette
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PaletteEntry {
+    pub name: &'static str,
+    /// placeholder for the argument, if the command takes one
+    pub argument: Option<&'static str>,
+    pub description: &'static str,
+    pub command: PaletteCommand,
+}
+
+pub const PALETTE_ENTRIES: &[PaletteEntry] = &[
+    PaletteEntry {
+        name: "flush cache",
+        argument: None,
+        description: "delete the DNS response cache",
+        command: PaletteCommand::FlushCache,
+    },
+    PaletteEntry {
+        name: "refresh lists",
+        argument: None,
+        description: "reload all blocking lists",
+        command: PaletteCommand::RefreshLists,
+    },
+    PaletteEntry {
+        name: "enable blocking",
+        argument: None,
+        description: "enable blocking for all groups",
+        command: PaletteCommand::EnableBlocking,
+    },
+    PaletteEntry {
+        name: "disable blocking",
+        argument: Some("[duration, e.g. 5m]"),
+        description: "disable blocking for all groups",
+        command: PaletteCommand::DisableBlocking,
+    },
+    PaletteEntry {
+        name: "query",
+        argument: Some("<domain> [type]"),
+        description: "resolve a domain via the blocky API",
+        command: PaletteCommand::Query,
+    },
+    PaletteEntry {
+        name: "check health",
+        argument: None,
+        description: "probe API, TCP and UDP ports",
+        command: PaletteCommand::CheckHealth,
+    },
+    PaletteEntry {
+        name: "event log",
+        argument: None,
+        description: "show all notifications",
+        command: PaletteCommand::EventLog,
+    },
+    PaletteEntry {
+        name: "logs",
+        argument: None,
+        description: "show the log viewer",
+        command: PaletteCommand::Logs,
+    },
+    PaletteEntry {
+        name: "edit config",
+        argument: None,
+        description: "open the config file in $EDITOR, then reload it",
+        command: PaletteCommand::EditConfig,
+    },
+    PaletteEntry {
+        name: "edit list",
+        argument: Some("<file>"),
+        description: "open in $EDITOR, then refresh the lists",
+        command: PaletteCommand::EditList,
+    },
+    PaletteEntry {
+        name: "bulk test",
+        argument: Some("[<file> [type]]"),
+        description: "query all domains of a list, shows the last results without a file",
+        command: PaletteCommand::BulkTest,
+    },
+    PaletteEntry {
+        name: "export results",
+        argument: Some("<file.csv|file.json>"),
+        description: "save the bulk test results in the shown order",
+        command: PaletteCommand::ExportResults,
+    },
+    PaletteEntry {
+        name: "run suite",
+        argument: Some("[<file.yaml>]"),
+        description: "check the expectations of a test suite, shows the last report without a file",
+        command: PaletteCommand::RunSuite,
+    },
+    PaletteEntry {
+        name: "watch",
+        argument: Some("<domain> [resolved|blocked|nxdomain]"),
+        description: "pin a domain to the watchlist, it is checked every watch_interval",
+        command: PaletteCommand::Watch,
+    },
+    PaletteEntry {
+        name: "unwatch",
+        argument: Some("<domain>"),
+        description: "remove a domain from the watchlist",
+        command: PaletteCommand::Unwatch,
+    },
+    PaletteEntry {
+        name: "help",
+        argument: None,
+        description: "show the key bindings",
+        command: PaletteCommand::Help,
+    },
+    PaletteEntry {
+        name: "quit",
+        argument: None,
+        description: "quit Blocky TUI",
+        command: PaletteCommand::Quit,
+    },
+];
+
+/// A palette entry matching the current input, together with the typed argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PaletteMatch {
+    pub entry: PaletteEntry,
+    pub argument: String,
+    score: i32,
+}
+
+/// State of the opened command palette
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct CommandPalette {
+    pub input: String,
+    /// index into the current matches
+    pub selected: usize,
+}
+
+impl CommandPalette {
+    /// All entries matching the input, best match first
+    pub fn matches(&self) -> Vec<PaletteMatch> {
+        let mut matches: Vec<PaletteMatch> = PALETTE_ENTRIES
+            .iter()
+            .filter_map(|entry| match_entry(entry, &self.input))
+            .collect();
+        // the sort is stable, equally scored entries keep the order of PALETTE_ENTRIES
+        matches.sort_by_key(|m| std::cmp::Reverse(m.score));
+        matches
+    }
+
+    pub fn selected_match(&self) -> Option<PaletteMatch> {
+        self.matches().into_iter().nth(self.selected)
+    }
+
+    pub fn select_next(&mut self) {
+        let len = self.matches().len().min(MAX_PALETTE_MATCHES);
+        if len > 0 {
+            self.selected = (self.selected + 1) % len;
+        }
+    }
+
+    pub fn select_previous(&mut self) {
+        let len = self.matches().len().min(MAX_PALETTE_MATCHES);
+        if len > 0 {
+            self.selected = (self.selected + len - 1) % len;
+        }
+    }
+
+    pub fn push_str(&mut self, s: &str) {
+        self.input.push_str(s);
+        self.selected = 0;
+    }
+
+    pub fn pop(&mut self) {
+        self.input.pop();
+        self.selected = 0;
+    }
+}
+
+/// Tries every split of the input into a command part and an argument part
+/// and returns the best scoring one.
+fn match_entry(entry: &PaletteEntry, input: &str) -> Option<PaletteMatch> {
+    let input = input.trim_start();
+    let mut splits = vec![(input.trim_end(), "")];
+    if entry.argument.is_some() {
+        splits.extend(
+            input
+                .char_indices()
+                .filter(|(_, c)| c.is_whitespace())
+                .map(|(i, _)| (&input[..i], input[i..].trim())),
+        );
+    }
+    splits
+        .into_iter()
+        .filter_map(|(command, argument)| {
+            fuzzy_score(command, entry.name).map(|score| (score, command.len(), argument))
+        })
+        // prefer the highest score, then the longest command part
+        .max_by_key(|(score, len, _)| (*score, *len))
+        .map(|(score, _, argument)| PaletteMatch {
+            entry: *entry,
+            argument: argument.to_string(),
+            score,
+        })
+}
+
+/// Scores how well `pattern` matches `candidate` as a case-insensitive subsequence
+///
+/// Consecutive characters and characters at the start of a word score higher.
+/// Returns `None` if `pattern` is not a subsequence of `candidate`.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+    for p in pattern.to_lowercase().chars() {
+        let offset = candidate[position..].iter().position(|c| *c == p)?;
+        let index = position + offset;
+        score += 1;
+        if previous_match.is_some_and(|prev| prev + 1 == index) {
+            score += 3;
+        }
+        if index == 0 || candidate[index - 1] == ' ' {
+            score += 2;
+        }
+        previous_match = Some(index);
+        position = index + 1;
+    }
+    Some(score)
+}
+
+/// Parses durations like `30s`, `5m`, `1h` or plain seconds
+pub fn parse_duration(input: &str) -> Result<Duration> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (value, unit) = input.split_at(split);
+    let value: u64 = value
+        .parse()
+        .map_err(|_| anyhow!("invalid duration '{input}'"))?;
+    let secs = match unit {
+        "" | "s" => value,
+        "m" => value * 60,
+        "h" => value * 60 * 60,
+        _ => bail!("invalid duration unit '{unit}', use s, m or h"),
+    };
+    Ok(Duration::from_secs(secs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn best_match(input: &str) -> Option<PaletteMatch> {
+        CommandPalette {
+            input: input.to_string(),
+            selected: 0,
+        }
+        .selected_match()
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("fc", "flush cache").is_some());
+        assert!(fuzzy_score("cf", "flush cache").is_none());
+        assert!(fuzzy_score("flush", "flush cache") > fuzzy_score("fsh", "flush cache"));
+    }
+
+    #[test]
+    fn test_palette_matches_commands_with_arguments() {
+        let m = best_match("flush").unwrap();
+        assert_eq!(m.entry.command, PaletteCommand::FlushCache);
+
+        let m = best_match("disable blocking 5m").unwrap();
+        assert_eq!(m.entry.command, PaletteCommand::DisableBlocking);
+        assert_eq!(m.argument, "5m");
+
+        let m = best_match("dis blo 10m").unwrap();
+        assert_eq!(m.entry.command, PaletteCommand::DisableBlocking);
+        assert_eq!(m.argument, "10m");
+
+        let m = best_match("query example.com AAAA").unwrap();
+        assert_eq!(m.entry.command, PaletteCommand::Query);
+        assert_eq!(m.argument, "example.com AAAA");
+
+        assert!(best_match("xyz").is_none());
+    }
+
+    #[test]
+    fn test_selection_wraps_around() {
+        let mut palette = CommandPalette::default();
+        palette.select_previous();
+        assert_eq!(palette.selected, MAX_PALETTE_MATCHES - 1);
+        palette.select_next();
+        assert_eq!(palette.selected, 0);
+    }
+
+    #[test]
+    fn test_parse_duration() -> Result<()> {
+        assert_eq!(parse_duration("30")?, Duration::from_secs(30));
+        assert_eq!(parse_duration("5m")?, Duration::from_secs(300));
+        assert_eq!(parse_duration("1h")?, Duration::from_secs(3600));
+        assert!(parse_duration("5d").is_err());
+        assert!(parse_duration("m").is_err());
+        Ok(())
+    }
+}
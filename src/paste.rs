@@ -0,0 +1,98 @@
+use url::Url;
+
+/// Cleans up pasted text for a single line text input
+///
+/// Only the first non-empty line is kept, tabs and other whitespace become a single space and
+/// the remaining control characters are removed.
+pub fn sanitize_paste(text: &str) -> String {
+    let line: String = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or_default()
+        .chars()
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .filter(|c| !c.is_control())
+        .collect();
+    line.split(' ')
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Like [`sanitize_paste`], but every word which looks like a URL is replaced by its hostname
+///
+/// `https://ads.example.com/path?x=1` and `ads.example.com:8080/path` both become `ads.example.com`.
+pub fn sanitize_domain_paste(text: &str) -> String {
+    sanitize_paste(text)
+        .split_whitespace()
+        .map(extract_hostname)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the hostname of a URL, other words are returned unchanged
+pub fn extract_hostname(word: &str) -> String {
+    if word.contains("://") {
+        if let Some(host) = Url::parse(word)
+            .ok()
+            .and_then(|u| u.host_str().map(String::from))
+        {
+            // IPv6 hosts are returned in brackets by the url crate
+            return host
+                .trim_start_matches('[')
+                .trim_end_matches(']')
+                .to_string();
+        }
+        return word.to_string();
+    }
+    // scheme-less URLs like "example.com/path" or "example.com:443"
+    let host = word.split(['/', '?', '#']).next().unwrap_or(word);
+    match host.rsplit_once(':') {
+        Some((name, port)) if !name.contains(':') && port.chars().all(|c| c.is_ascii_digit()) => {
+            name.to_string()
+        }
+        _ => host.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_paste() {
+        assert_eq!(sanitize_paste("  example.com \n"), "example.com");
+        assert_eq!(sanitize_paste("\n\nfirst\nsecond"), "first");
+        assert_eq!(sanitize_paste("a\tb\u{7}c"), "a bc");
+        assert_eq!(sanitize_paste("a \t  b"), "a b");
+        assert_eq!(sanitize_paste(""), "");
+    }
+
+    #[test]
+    fn test_extract_hostname() {
+        assert_eq!(
+            extract_hostname("https://ads.example.com/path?x=1"),
+            "ads.example.com"
+        );
+        assert_eq!(extract_hostname("http://example.com:8080"), "example.com");
+        assert_eq!(extract_hostname("ads.example.com/path"), "ads.example.com");
+        assert_eq!(extract_hostname("example.com:443"), "example.com");
+        assert_eq!(extract_hostname("http://[2001:db8::1]/x"), "2001:db8::1");
+        assert_eq!(extract_hostname("2001:db8::1"), "2001:db8::1");
+        assert_eq!(extract_hostname("AAAA"), "AAAA");
+    }
+
+    #[test]
+    fn test_sanitize_domain_paste() {
+        assert_eq!(
+            sanitize_domain_paste("query https://ads.example.com/path?x=1\n"),
+            "query ads.example.com"
+        );
+        // a line of a hosts file
+        assert_eq!(
+            sanitize_domain_paste("0.0.0.0\tads.example.com"),
+            "0.0.0.0 ads.example.com"
+        );
+    }
+}
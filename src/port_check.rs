@@ -1,66 +1,274 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::Path;
 use std::str::FromStr;
 use std::time::Duration;
 
 use anyhow::{anyhow, Result};
+use futures::future::join_all;
 use rustdns::{Message, Type};
-use tokio::net::{TcpStream, UdpSocket};
+use tokio::net::{lookup_host, TcpStream, UdpSocket};
 use tokio::time::timeout;
-use tracing::debug;
-use url::Url;
+use tracing::{debug, warn};
+use url::{Host, Url};
 
 use crate::api::DNSQuery;
 
+/// Time after which an unanswered probe counts as closed
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum PortState {
     Open,
+    /// open on some of the server's addresses and closed or failing on the others
+    Partial,
     Closed,
     Error,
+    /// not probed because the API is only reachable through a proxy
+    Skipped,
+}
+
+impl PortState {
+    /// Combines the results of all addresses of a server into one state
+    pub fn summarize(probes: &[AddressProbe]) -> Self {
+        let all = |state| probes.iter().all(|probe| probe.state == state);
+        let any = |state| probes.iter().any(|probe| probe.state == state);
+        if probes.is_empty() || all(PortState::Error) {
+            PortState::Error
+        } else if all(PortState::Open) {
+            PortState::Open
+        } else if any(PortState::Open) {
+            PortState::Partial
+        } else {
+            PortState::Closed
+        }
+    }
+}
+
+/// Probe result of a single address of the server
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct AddressProbe {
+    pub addr: SocketAddr,
+    pub state: PortState,
 }
 
-pub async fn check_tcp_port(host: String, tcp_port: u16) -> Result<PortState> {
+/// Resolves the host of the URL to all of its IPv4 and IPv6 addresses
+pub async fn resolve(host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+    let url = Url::parse(host)?;
+    let addrs: Vec<SocketAddr> = match url.host() {
+        Some(Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+        Some(Host::Domain(domain)) => lookup_host((domain, port)).await?.collect(),
+        None => return Err(anyhow!("could not get host from host string")),
+    };
+    // resolvers may return the same address once per socket type
+    let mut unique = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        if !unique.contains(&addr) {
+            unique.push(addr);
+        }
+    }
+    if unique.is_empty() {
+        return Err(anyhow!("{host} did not resolve to any address"));
+    }
+    debug!("{host} resolved to {unique:?}");
+    Ok(unique)
+}
+
+pub async fn check_tcp_port(host: String, tcp_port: u16) -> Result<Vec<AddressProbe>> {
     debug!("checking API server TCP port by manually creating a connection");
-    if let Some(domain) = Url::parse(&host)?.host_str() {
-        match TcpStream::connect(format!("{domain}:{tcp_port}")).await {
-            Ok(_) => Ok(PortState::Open),
-            Err(r) => {
-                debug!("could not create TCPStream: {r}");
-                Ok(PortState::Closed)
+    let addrs = resolve(&host, tcp_port).await?;
+    Ok(join_all(addrs.into_iter().map(|addr| async move {
+        let state = match timeout(PROBE_TIMEOUT, TcpStream::connect(addr)).await {
+            Ok(Ok(_)) => PortState::Open,
+            Ok(Err(r)) => {
+                debug!("could not create TCPStream to {addr}: {r}");
+                PortState::Closed
+            }
+            Err(_) => {
+                debug!("connecting to {addr} timed out");
+                PortState::Closed
             }
+        };
+        AddressProbe { addr, state }
+    }))
+    .await)
+}
+
+/// Checks whether the Unix socket of the API accepts connections
+#[cfg(unix)]
+pub async fn check_unix_socket(path: &Path) -> Result<PortState> {
+    debug!("checking API server Unix socket {path:?}");
+    match tokio::net::UnixStream::connect(path).await {
+        Ok(_) => Ok(PortState::Open),
+        Err(r) => {
+            debug!("could not connect to Unix socket: {r}");
+            Ok(PortState::Closed)
         }
-    } else {
-        Err(anyhow!("could not get host from host string"))
     }
 }
 
-pub async fn check_dns(host: String, udp_port: u16, query: DNSQuery) -> Result<PortState> {
+#[cfg(not(unix))]
+pub async fn check_unix_socket(path: &Path) -> Result<PortState> {
+    Err(anyhow!(
+        "Unix sockets are not supported on this platform: {path:?}"
+    ))
+}
+
+pub async fn check_dns(host: String, udp_port: u16, query: DNSQuery) -> Result<Vec<AddressProbe>> {
     debug!("checking DNS by manually quering it");
-    if let Some(domain) = Url::parse(&host)?.host_str() {
-        let sock = UdpSocket::bind("localhost:9641").await?;
-        sock.connect(format!("{domain}:{udp_port}")).await?;
-
-        let mut m = Message::default();
-        // NOTE: this could cause more troubles than manually parsing it
-        let query_type = Type::from_str(query.query_type)?;
-        m.add_question(domain, query_type, rustdns::Class::Internet);
-
-        let question = m.to_vec()?;
-        sock.send(&question).await?;
-
-        let mut resp = [0; 4096];
-        // NOTE: the udp port is seen as closed only when the response times out
-        let len = match timeout(Duration::from_secs(5), sock.recv(&mut resp)).await {
-            Ok(resp_res) => resp_res?,
-            Err(timeout) => {
-                debug!("UDP DNS request timed out: {timeout}");
-                return Ok(PortState::Closed);
+    let addrs = resolve(&host, udp_port).await?;
+    let question = encode_question(&query)?;
+
+    let probes = join_all(addrs.iter().map(|addr| probe_dns(*addr, &question))).await;
+    Ok(addrs
+        .into_iter()
+        .zip(probes)
+        .map(|(addr, state)| {
+            // e.g. IPv6 is unreachable, which must not hide the result of the other addresses
+            let state = state.unwrap_or_else(|err| {
+                warn!("DNS probe of {addr} failed: {err:#}");
+                PortState::Error
+            });
+            AddressProbe { addr, state }
+        })
+        .collect())
+}
+
+/// Answer of the DNS server to a single question
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsAnswer {
+    /// e.g. `NOERROR` or `NXDOMAIN`, like the return codes reported by the API
+    pub return_code: String,
+    /// data of the answer records, e.g. `192.0.2.1`
+    pub records: Vec<String>,
+}
+
+/// Sends the query directly to the DNS port, bypassing the API
+///
+/// Only the first address of the server is asked.
+pub async fn query_dns(host: String, udp_port: u16, query: &DNSQuery) -> Result<DnsAnswer> {
+    let addr = resolve(&host, udp_port).await?[0];
+    let question = encode_question(query)?;
+    let Some(answer) = exchange(addr, &question).await? else {
+        return Err(anyhow!("no DNS answer from {addr}"));
+    };
+    Ok(DnsAnswer {
+        return_code: format!("{:?}", answer.rcode).to_uppercase(),
+        records: answer
+            .answers
+            .iter()
+            .map(|record| record.resource.to_string())
+            .collect(),
+    })
+}
+
+fn encode_question(query: &DNSQuery) -> Result<Vec<u8>> {
+    let mut m = Message::default();
+    // NOTE: this could cause more troubles than manually parsing it
+    let query_type = Type::from_str(&query.query_type)?;
+    m.add_question(&query.query, query_type, rustdns::Class::Internet);
+    Ok(m.to_vec()?)
+}
+
+/// Sends the question from an ephemeral port of the address family of `addr`
+async fn probe_dns(addr: SocketAddr, question: &[u8]) -> Result<PortState> {
+    // NOTE: the udp port is seen as closed only when the response times out
+    match exchange(addr, question).await? {
+        Some(_) => Ok(PortState::Open),
+        None => Ok(PortState::Closed),
+    }
+}
+
+/// Sends the question and waits for the answer, `None` if the server did not answer
+async fn exchange(addr: SocketAddr, question: &[u8]) -> Result<Option<Message>> {
+    let local: SocketAddr = match addr {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let sock = UdpSocket::bind(local).await?;
+    sock.connect(addr).await?;
+    sock.send(question).await?;
+
+    let mut resp = [0; 4096];
+    let len = match timeout(PROBE_TIMEOUT, sock.recv(&mut resp)).await {
+        Ok(Ok(len)) => len,
+        // e.g. ICMP port unreachable, reported as connection refused
+        Ok(Err(r)) => {
+            debug!("UDP DNS request to {addr} failed: {r}");
+            return Ok(None);
+        }
+        Err(timeout) => {
+            debug!("UDP DNS request to {addr} timed out: {timeout}");
+            return Ok(None);
+        }
+    };
+
+    let answer = Message::from_slice(&resp[0..len])?;
+    debug!("received dns response from {addr}: {answer}");
+    Ok(Some(answer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn probe(addr: &str, state: PortState) -> AddressProbe {
+        AddressProbe {
+            addr: addr.parse().unwrap(),
+            state,
+        }
+    }
+
+    #[test]
+    fn test_summarize() {
+        let v4_open = probe("127.0.0.1:53", PortState::Open);
+        let v6_open = probe("[::1]:53", PortState::Open);
+        let v6_closed = probe("[::1]:53", PortState::Closed);
+        let v6_error = probe("[::1]:53", PortState::Error);
+        assert_eq!(PortState::summarize(&[v4_open, v6_open]), PortState::Open);
+        assert_eq!(
+            PortState::summarize(&[v4_open, v6_closed]),
+            PortState::Partial
+        );
+        assert_eq!(PortState::summarize(&[v6_closed]), PortState::Closed);
+        assert_eq!(
+            PortState::summarize(&[v4_open, v6_error]),
+            PortState::Partial
+        );
+        assert_eq!(PortState::summarize(&[v6_error]), PortState::Error);
+        assert_eq!(PortState::summarize(&[]), PortState::Error);
+    }
+
+    #[tokio::test]
+    async fn test_check_dns_reports_failing_address() -> Result<()> {
+        // answers every question with garbage which can not be decoded
+        let server = UdpSocket::bind("127.0.0.1:0").await?;
+        let addr = server.local_addr()?;
+        tokio::spawn(async move {
+            let mut buf = [0; 512];
+            while let Ok((_, peer)) = server.recv_from(&mut buf).await {
+                let _ = server.send_to(&[0xff], peer).await;
             }
+        });
+        let query = DNSQuery {
+            query: "example.com".to_string(),
+            query_type: "A".to_string(),
         };
+        let probes = check_dns("http://127.0.0.1".to_string(), addr.port(), query).await?;
+        assert_eq!(probes, [probe(&addr.to_string(), PortState::Error)]);
+        Ok(())
+    }
 
-        let answer = Message::from_slice(&resp[0..len])?;
-        debug!("received dns response: {answer}");
-
-        Ok(PortState::Open)
-    } else {
-        Err(anyhow!("could not get host from host string"))
+    #[tokio::test]
+    async fn test_resolve_ip_literals() -> Result<()> {
+        assert_eq!(
+            resolve("http://[::1]", 4000).await?,
+            ["[::1]:4000".parse::<SocketAddr>()?]
+        );
+        assert_eq!(
+            resolve("https://192.0.2.1/blocky", 53).await?,
+            ["192.0.2.1:53".parse::<SocketAddr>()?]
+        );
+        Ok(())
     }
 }
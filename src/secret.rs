@@ -0,0 +1,121 @@
+use std::{fs::Metadata, path::Path, process::Command};
+
+use anyhow::{anyhow, bail, Context, Result};
+use tracing::{debug, warn};
+
+/// Resolves a config value which may reference a secret instead of containing it
+///
+/// - `env:VAR` reads the environment variable `VAR`
+/// - `file:/path` reads the file, a trailing newline is removed
+/// - `cmd:command` runs the command with the system shell and uses the first line of its output,
+///   e.g. `cmd:pass show blocky`
+///
+/// Any other value is returned unchanged.
+pub fn resolve_secret(value: &str) -> Result<String> {
+    if let Some(var) = value.strip_prefix("env:") {
+        std::env::var(var).map_err(|_| anyhow!("environment variable {var} is not set"))
+    } else if let Some(path) = value.strip_prefix("file:") {
+        read_secret_file(Path::new(path))
+    } else if let Some(command) = value.strip_prefix("cmd:") {
+        run_secret_command(command)
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+fn read_secret_file(path: &Path) -> Result<String> {
+    let metadata =
+        std::fs::metadata(path).with_context(|| format!("could not read secret file {path:?}"))?;
+    if is_world_readable(&metadata) {
+        warn!("secret file {path:?} is readable by all users, restrict it with `chmod o-r`");
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read secret file {path:?}"))?;
+    Ok(content.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(unix)]
+fn is_world_readable(metadata: &Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o004 != 0
+}
+
+/// Windows has no mode bits, access is controlled by ACLs
+#[cfg(not(unix))]
+fn is_world_readable(_metadata: &Metadata) -> bool {
+    false
+}
+
+fn run_secret_command(command: &str) -> Result<String> {
+    // the command itself is logged, never its output
+    debug!("running secret command '{command}'");
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .with_context(|| format!("could not run secret command '{command}'"))?;
+    if !output.status.success() {
+        bail!(
+            "secret command '{command}' failed with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .with_context(|| format!("output of secret command '{command}' is not UTF-8"))?;
+    match stdout.lines().next() {
+        Some(line) if !line.is_empty() => Ok(line.to_string()),
+        _ => bail!("secret command '{command}' printed nothing"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_secret() -> Result<()> {
+        assert_eq!(resolve_secret("plain")?, "plain");
+
+        std::env::set_var("BLOCKY_TUI_TEST_SECRET", "from-env");
+        assert_eq!(resolve_secret("env:BLOCKY_TUI_TEST_SECRET")?, "from-env");
+        assert!(resolve_secret("env:BLOCKY_TUI_TEST_MISSING").is_err());
+
+        let path = std::env::temp_dir().join(format!("blocky-tui-secret-{}", std::process::id()));
+        std::fs::write(&path, "from-file\n")?;
+        assert_eq!(
+            resolve_secret(&format!("file:{}", path.display()))?,
+            "from-file"
+        );
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_secret_command() -> Result<()> {
+        assert_eq!(
+            resolve_secret("cmd:printf 'secret\\nmetadata: x\\n'")?,
+            "secret"
+        );
+        assert!(resolve_secret("cmd:exit 1").is_err());
+        assert!(resolve_secret("cmd:true").is_err());
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_world_readable_secret_file() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("blocky-tui-perms-{}", std::process::id()));
+        std::fs::write(&path, "secret")?;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644))?;
+        assert!(is_world_readable(&std::fs::metadata(&path)?));
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+        assert!(!is_world_readable(&std::fs::metadata(&path)?));
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}
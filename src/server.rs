@@ -0,0 +1,108 @@
+use std::{collections::BTreeMap, path::PathBuf};
+
+use serde::Deserialize;
+
+/// Connection settings of a blocky instance, configured in `[servers.<name>]`
+///
+/// Secret values (passwords, tokens and header values) may reference the environment or
+/// a file or a command with `env:VAR`, `file:/path` or `cmd:command`, see [`crate::secret::resolve_secret`].
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ServerProfile {
+    /// base URL of the API, may contain a path prefix like `https://example.com/blocky/`
+    pub url: String,
+    /// port of the API, defaults to the port of the URL or the default port of its scheme
+    #[serde(default)]
+    pub api_port: Option<u16>,
+    /// UDP port of the DNS server
+    #[serde(default = "default_dns_port")]
+    pub dns_port: u16,
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+    #[serde(default)]
+    pub tls: TlsConfig,
+    /// headers added to every API request, e.g. for reverse proxies
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// proxy for API requests, e.g. `http://proxy:3128` or `socks5h://jump-host:1080`
+    #[serde(default)]
+    pub proxy: Option<String>,
+    /// Unix domain socket serving the API, the host of `url` is then only used for display
+    #[serde(default)]
+    pub unix_socket: Option<PathBuf>,
+}
+
+fn default_dns_port() -> u16 {
+    53
+}
+
+impl Default for ServerProfile {
+    /// a local blocky like the one started by `test/run-test-container.sh`
+    fn default() -> Self {
+        Self {
+            url: "http://localhost".to_string(),
+            api_port: Some(4000),
+            dns_port: 1234,
+            auth: None,
+            tls: TlsConfig::default(),
+            headers: BTreeMap::new(),
+            proxy: None,
+            unix_socket: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum AuthConfig {
+    Basic { username: String, password: String },
+    Bearer { token: String },
+}
+
+#[derive(Debug, Default, Clone, Deserialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM bundle of additional trusted CA certificates, e.g. for self-signed certificates
+    pub ca_cert: Option<PathBuf>,
+    /// PEM client certificate, requires `client_key`
+    pub client_cert: Option<PathBuf>,
+    /// PEM (PKCS#8) private key of the client certificate
+    pub client_key: Option<PathBuf>,
+    /// skip certificate validation entirely, only use this for testing
+    pub insecure: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_server_profile() {
+        let profile: ServerProfile = toml::from_str(
+            r#"
+            url = "https://dns.example.com/blocky/"
+            auth = { basic = { username = "admin", password = "env:BLOCKY_PASSWORD" } }
+            tls = { ca_cert = "/etc/ssl/ca.pem" }
+            headers = { X-Api-Key = "file:/run/secrets/key" }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(profile.api_port, None);
+        assert_eq!(profile.dns_port, 53);
+        assert_eq!(
+            profile.auth,
+            Some(AuthConfig::Basic {
+                username: "admin".to_string(),
+                password: "env:BLOCKY_PASSWORD".to_string()
+            })
+        );
+        assert_eq!(profile.tls.ca_cert, Some(PathBuf::from("/etc/ssl/ca.pem")));
+        assert_eq!(profile.headers["X-Api-Key"], "file:/run/secrets/key");
+
+        assert!(toml::from_str::<ServerProfile>(
+            r#"url = "x"
+            passwd = "typo""#
+        )
+        .is_err());
+    }
+}
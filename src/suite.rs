@@ -0,0 +1,841 @@
+//! Expectations about how blocky answers domains, run against one or more servers as a smoke
+//! test after a deployment
+//!
+//! A suite is a YAML file:
+//!
+//! ```yaml
+//! servers: [home, office]
+//! tests:
+//!   - domain: example.com
+//!     expect: resolved
+//!   - domain: doubleclick.net
+//!     expect: blocked
+//!     reason: ads
+//!   - domain: intranet.corp
+//!     answer: 10.0.0.5
+//!     via: both
+//! ```
+//!
+//! Servers are names of server profiles of the config, the default server is used if there are
+//! none. Every expectation is checked via the query API, the DNS port or both.
+
+use std::{
+    fmt::Write,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use futures::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{ApiClient, DNSQuery, DNSResponse};
+use crate::config::Config;
+use crate::port_check::{self, DnsAnswer};
+use crate::server::ServerProfile;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    /// names of server profiles, the default server if empty
+    #[serde(default)]
+    pub servers: Vec<String>,
+    pub tests: Vec<Expectation>,
+}
+
+impl Suite {
+    pub fn parse(content: &str) -> Result<Self> {
+        let suite: Suite = serde_yaml::from_str(content)?;
+        if suite.tests.is_empty() {
+            bail!("the suite does not contain any test");
+        }
+        suite.tests.iter().try_for_each(Expectation::validate)?;
+        Ok(suite)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read test suite {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("invalid test suite {}", path.display()))
+    }
+
+    /// Number of checks per server, expectations via both routes are checked twice
+    pub fn checks(&self) -> usize {
+        self.tests.iter().map(|e| e.via.routes().len()).sum()
+    }
+}
+
+/// How a domain must be answered, at least one of `expect`, `reason` and `answer` is set
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectation {
+    /// shown in reports, generated from the checks if omitted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub domain: String,
+    #[serde(rename = "type", default = "default_query_type")]
+    pub query_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expect: Option<Outcome>,
+    /// part of the reason given by blocky, ignoring case, e.g. the name of the deny list group
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// an address or name which must be part of the answer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answer: Option<String>,
+    #[serde(default)]
+    pub via: Via,
+}
+
+fn default_query_type() -> String {
+    "A".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// answered with NOERROR and not blocked
+    Resolved,
+    /// via DNS this means an answer with only zero addresses, like blocky's default `zeroIp`
+    Blocked,
+    NxDomain,
+}
+
+impl Outcome {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Outcome::Resolved => "RESOLVED",
+            Outcome::Blocked => "BLOCKED",
+            Outcome::NxDomain => "NXDOMAIN",
+        }
+    }
+
+    /// The outcome of an API response, `None` for other return codes like SERVFAIL
+    fn of_response(response: &DNSResponse) -> Option<Self> {
+        if response.response_type == "BLOCKED" {
+            return Some(Outcome::Blocked);
+        }
+        Self::of_return_code(&response.return_code, false)
+    }
+
+    /// The outcome of a DNS answer, a blocked domain looks like a resolved one via DNS otherwise
+    fn of_answer(answer: &DnsAnswer) -> Option<Self> {
+        let zero_ips = !answer.records.is_empty()
+            && answer
+                .records
+                .iter()
+                .all(|r| r.parse::<IpAddr>().is_ok_and(|ip| ip.is_unspecified()));
+        Self::of_return_code(&answer.return_code, zero_ips)
+    }
+
+    /// Parses the names used in suite files, e.g. `blocked`
+    pub fn parse(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "resolved" => Ok(Outcome::Resolved),
+            "blocked" => Ok(Outcome::Blocked),
+            "nxdomain" => Ok(Outcome::NxDomain),
+            _ => bail!("unknown outcome '{name}', expected resolved, blocked or nxdomain"),
+        }
+    }
+
+    fn of_return_code(return_code: &str, blocked: bool) -> Option<Self> {
+        match return_code {
+            "NOERROR" if blocked => Some(Outcome::Blocked),
+            "NOERROR" => Some(Outcome::Resolved),
+            "NXDOMAIN" => Some(Outcome::NxDomain),
+            _ => None,
+        }
+    }
+}
+
+/// Route the query takes to the server
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Via {
+    /// blocky's query API, the only route which reports the reason
+    #[default]
+    Api,
+    /// a UDP query to the DNS port, as clients see it
+    Dns,
+    Both,
+}
+
+impl Via {
+    pub fn routes(&self) -> &'static [Route] {
+        match self {
+            Via::Api => &[Route::Api],
+            Via::Dns => &[Route::Dns],
+            Via::Both => &[Route::Api, Route::Dns],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Api,
+    Dns,
+}
+
+impl Route {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Route::Api => "api",
+            Route::Dns => "dns",
+        }
+    }
+}
+
+impl Expectation {
+    /// Expects the outcome of the A query of the domain via the API, e.g. for the watchlist
+    pub fn new(domain: &str, expect: Outcome) -> Self {
+        Self {
+            name: None,
+            domain: domain.to_string(),
+            query_type: default_query_type(),
+            expect: Some(expect),
+            reason: None,
+            answer: None,
+            via: Via::default(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.expect.is_none() && self.reason.is_none() && self.answer.is_none() {
+            bail!(
+                "{}: nothing to check, set expect, reason or answer",
+                self.domain
+            );
+        }
+        if self.reason.is_some() && self.via != Via::Api {
+            bail!(
+                "{}: the reason is only reported by the API, check it via api",
+                self.domain
+            );
+        }
+        Ok(())
+    }
+
+    /// The name or a generated one, e.g. `doubleclick.net A must be BLOCKED with reason ads`
+    pub fn label(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        let mut checks = Vec::new();
+        match self.expect {
+            Some(Outcome::Resolved) => checks.push("resolve".to_string()),
+            Some(outcome) => checks.push(format!("be {}", outcome.label())),
+            None => {}
+        }
+        if let Some(answer) = &self.answer {
+            checks.push(format!("return {answer}"));
+        }
+        let mut label = format!(
+            "{} {} must {}",
+            self.domain,
+            self.query_type.to_uppercase(),
+            checks.join(" and ")
+        );
+        if let Some(reason) = &self.reason {
+            if checks.is_empty() {
+                label.push_str("have");
+            } else {
+                label.push_str(" with");
+            }
+            let _ = write!(label, " reason {reason}");
+        }
+        label
+    }
+
+    pub fn query(&self) -> DNSQuery {
+        DNSQuery {
+            query: self.domain.clone(),
+            query_type: self.query_type.to_uppercase(),
+        }
+    }
+
+    /// Checks a response of the query API, the error describes the first mismatch
+    pub fn check_response(&self, response: &DNSResponse) -> Result<(), String> {
+        if let Some(expected) = self.expect {
+            if Outcome::of_response(response) != Some(expected) {
+                return Err(format!(
+                    "expected {}, got {} ({})",
+                    expected.label(),
+                    response.response_type,
+                    response.reason
+                ));
+            }
+        }
+        if let Some(reason) = &self.reason {
+            if !response
+                .reason
+                .to_lowercase()
+                .contains(&reason.to_lowercase())
+            {
+                return Err(format!("expected reason {reason}, got {}", response.reason));
+            }
+        }
+        if let Some(answer) = &self.answer {
+            if !has_answer(&response.response, answer) {
+                return Err(format!(
+                    "expected answer {answer}, got {}",
+                    response.response
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks an answer of the DNS port, the error describes the first mismatch
+    pub fn check_answer(&self, answer: &DnsAnswer) -> Result<(), String> {
+        let records = answer.records.join(", ");
+        if let Some(expected) = self.expect {
+            if Outcome::of_answer(answer) != Some(expected) {
+                return Err(format!(
+                    "expected {}, got {} [{records}]",
+                    expected.label(),
+                    answer.return_code
+                ));
+            }
+        }
+        if let Some(expected) = &self.answer {
+            if !has_answer(&records, expected) {
+                return Err(format!("expected answer {expected}, got [{records}]"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Queries the server via the route and checks its answer
+    pub async fn check(&self, api: &ApiClient, route: Route) -> Verdict {
+        let query = self.query();
+        let checked = match route {
+            Route::Api => api
+                .post_dnsquery(query)
+                .await
+                .map(|response| self.check_response(&response))
+                .map_err(|err| err.to_string()),
+            Route::Dns => port_check::query_dns(api.url.to_string(), api.dns_port, &query)
+                .await
+                .map(|answer| self.check_answer(&answer))
+                .map_err(|err| format!("{err:#}")),
+        };
+        match checked {
+            Ok(Ok(())) => Verdict::Pass,
+            Ok(Err(mismatch)) => Verdict::Fail(mismatch),
+            Err(err) => Verdict::Error(err),
+        }
+    }
+
+    /// Checks all routes of `via`, the first route which does not pass decides
+    pub async fn check_all(&self, api: &ApiClient) -> Verdict {
+        for route in self.via.routes() {
+            match self.check(api, *route).await {
+                Verdict::Pass => {}
+                verdict => return verdict,
+            }
+        }
+        Verdict::Pass
+    }
+}
+
+/// Whether one of the records in e.g. `A (10.0.0.5), A (10.0.0.6)` is the expected one
+///
+/// Names match regardless of case and of the trailing dot.
+fn has_answer(records: &str, expected: &str) -> bool {
+    let expected = expected.trim_end_matches('.');
+    records
+        .split(|c: char| c.is_whitespace() || matches!(c, '(' | ')' | ','))
+        .any(|record| record.trim_end_matches('.').eq_ignore_ascii_case(expected))
+}
+
+/// A server the suite is run against
+#[derive(Debug, Clone)]
+pub struct Target {
+    /// name of the server profile
+    pub name: String,
+    pub api: ApiClient,
+}
+
+/// Profiles of the servers with the given names
+pub fn profiles(config: &Config, names: &[String]) -> Result<Vec<(String, ServerProfile)>> {
+    names
+        .iter()
+        .map(|name| {
+            let profile = config
+                .servers
+                .get(name)
+                .ok_or_else(|| anyhow!("server '{name}' is not configured in [servers]"))?;
+            Ok((name.clone(), profile.clone()))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Pass,
+    /// the server answered differently than expected
+    Fail(String),
+    /// the server could not be asked
+    Error(String),
+}
+
+/// Result of checking one expectation against one server via one route
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseResult {
+    pub server: String,
+    pub name: String,
+    pub route: Route,
+    pub verdict: Verdict,
+    pub duration: Duration,
+}
+
+impl CaseResult {
+    pub fn passed(&self) -> bool {
+        self.verdict == Verdict::Pass
+    }
+
+    /// e.g. `example.com A must resolve (api)`
+    pub fn title(&self) -> String {
+        format!("{} ({})", self.name, self.route.label())
+    }
+}
+
+/// Checks all expectations against all targets with at most `concurrency` queries in flight
+///
+/// Results are yielded in the order of the targets and the suite.
+pub fn run(
+    targets: Vec<Target>,
+    tests: Vec<Expectation>,
+    concurrency: usize,
+) -> impl Stream<Item = CaseResult> {
+    let cases: Vec<(Target, Expectation, Route)> = targets
+        .iter()
+        .flat_map(|target| {
+            tests.iter().flat_map(move |expectation| {
+                expectation
+                    .via
+                    .routes()
+                    .iter()
+                    .map(move |route| (target.clone(), expectation.clone(), *route))
+            })
+        })
+        .collect();
+    stream::iter(cases)
+        .map(|(target, expectation, route)| async move {
+            let start = Instant::now();
+            let verdict = expectation.check(&target.api, route).await;
+            CaseResult {
+                server: target.name,
+                name: expectation.label(),
+                route,
+                verdict,
+                duration: start.elapsed(),
+            }
+        })
+        .buffered(concurrency.max(1))
+}
+
+/// Like [`run`], but the clients are built from the profiles one server after the other
+///
+/// Resolving the secrets of a profile may run commands, so it happens on a blocking thread. All
+/// checks of a server whose client can not be built fail with an error.
+pub fn run_profiles(
+    profiles: Vec<(String, ServerProfile)>,
+    tests: Vec<Expectation>,
+    concurrency: usize,
+) -> impl Stream<Item = CaseResult> {
+    stream::iter(profiles)
+        .then(move |(name, profile)| {
+            let tests = tests.clone();
+            async move {
+                let api = tokio::task::spawn_blocking(move || ApiClient::from_profile(&profile))
+                    .await
+                    .map_err(|err| err.to_string())
+                    .and_then(|api| api.map_err(|err| err.to_string()));
+                match api {
+                    Ok(api) => run(vec![Target { name, api }], tests, concurrency).boxed(),
+                    Err(err) => stream::iter(unreachable(&name, &tests, &err)).boxed(),
+                }
+            }
+        })
+        .flatten()
+}
+
+/// Results of all checks of a server which could not be asked at all
+fn unreachable(server: &str, tests: &[Expectation], message: &str) -> Vec<CaseResult> {
+    tests
+        .iter()
+        .flat_map(|expectation| {
+            expectation.via.routes().iter().map(|route| CaseResult {
+                server: server.to_string(),
+                name: expectation.label(),
+                route: *route,
+                verdict: Verdict::Error(message.to_string()),
+                duration: Duration::ZERO,
+            })
+        })
+        .collect()
+}
+
+/// Number of passed, failed and errored checks
+pub fn count(results: &[CaseResult]) -> (usize, usize, usize) {
+    results
+        .iter()
+        .fold((0, 0, 0), |(pass, fail, error), result| {
+            match result.verdict {
+                Verdict::Pass => (pass + 1, fail, error),
+                Verdict::Fail(_) => (pass, fail + 1, error),
+                Verdict::Error(_) => (pass, fail, error + 1),
+            }
+        })
+}
+
+/// e.g. `5 checks: 4 passed, 1 failed, 0 errors`
+pub fn summary_line(results: &[CaseResult]) -> String {
+    let (passed, failed, errors) = count(results);
+    format!(
+        "{} checks: {passed} passed, {failed} failed, {errors} errors",
+        results.len()
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    /// Test Anything Protocol, version 13
+    Tap,
+    /// JUnit XML, as read by most CI servers
+    Junit,
+}
+
+impl ReportFormat {
+    /// JUnit for `.xml` files, TAP for all other files
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("xml") => ReportFormat::Junit,
+            _ => ReportFormat::Tap,
+        }
+    }
+}
+
+pub fn report(results: &[CaseResult], format: ReportFormat) -> String {
+    match format {
+        ReportFormat::Tap => to_tap(results),
+        ReportFormat::Junit => to_junit(results),
+    }
+}
+
+fn to_tap(results: &[CaseResult]) -> String {
+    let mut tap = format!("TAP version 13\n1..{}\n", results.len());
+    for (number, result) in results.iter().enumerate() {
+        let status = if result.passed() { "ok" } else { "not ok" };
+        let _ = writeln!(
+            tap,
+            "{status} {} - [{}] {}",
+            number + 1,
+            result.server,
+            result.title()
+        );
+        let (severity, message) = match &result.verdict {
+            Verdict::Pass => continue,
+            Verdict::Fail(message) => ("fail", message),
+            Verdict::Error(message) => ("error", message),
+        };
+        // JSON strings are valid in the YAML diagnostics block
+        let message = serde_json::Value::from(message.as_str());
+        let _ = write!(
+            tap,
+            "  ---\n  message: {message}\n  severity: {severity}\n  ...\n"
+        );
+    }
+    tap
+}
+
+/// One `testsuite` per server, in the order the servers were tested
+fn to_junit(results: &[CaseResult]) -> String {
+    let mut servers: Vec<&str> = Vec::new();
+    for result in results {
+        if !servers.contains(&result.server.as_str()) {
+            servers.push(&result.server);
+        }
+    }
+    let seconds = |results: &[&CaseResult]| {
+        let total: Duration = results.iter().map(|r| r.duration).sum();
+        format!("{:.3}", total.as_secs_f64())
+    };
+
+    let all: Vec<&CaseResult> = results.iter().collect();
+    let (_, failed, errors) = count(results);
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"blocky-tui\" tests=\"{}\" failures=\"{failed}\" errors=\"{errors}\" time=\"{}\">",
+        results.len(),
+        seconds(&all)
+    );
+    for server in servers {
+        let cases: Vec<&CaseResult> = results.iter().filter(|r| r.server == server).collect();
+        let failed = cases
+            .iter()
+            .filter(|r| matches!(r.verdict, Verdict::Fail(_)))
+            .count();
+        let errors = cases
+            .iter()
+            .filter(|r| matches!(r.verdict, Verdict::Error(_)))
+            .count();
+        let server = escape_xml(server);
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"{server}\" tests=\"{}\" failures=\"{failed}\" errors=\"{errors}\" time=\"{}\">",
+            cases.len(),
+            seconds(&cases)
+        );
+        for case in cases {
+            let _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{server}\" time=\"{:.3}\"",
+                escape_xml(&case.title()),
+                case.duration.as_secs_f64()
+            );
+            let (element, message) = match &case.verdict {
+                Verdict::Pass => {
+                    xml.push_str("/>\n");
+                    continue;
+                }
+                Verdict::Fail(message) => ("failure", message),
+                Verdict::Error(message) => ("error", message),
+            };
+            let _ = write!(
+                xml,
+                ">\n      <{element} message=\"{}\"/>\n    </testcase>\n",
+                escape_xml(message)
+            );
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Escapes text for attribute values and element content
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // line breaks in attributes would be normalized to spaces by parsers
+            '\n' => escaped.push_str("&#10;"),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// State of the test suite report screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuiteRun {
+    /// the suite file
+    pub source: PathBuf,
+    /// number of checks of all servers
+    pub total: usize,
+    /// results received so far, in the order of the suite
+    pub results: Vec<CaseResult>,
+    /// index of the first shown row
+    pub scroll: usize,
+}
+
+impl SuiteRun {
+    pub fn new(source: PathBuf, total: usize) -> Self {
+        Self {
+            source,
+            total,
+            results: Vec::with_capacity(total),
+            scroll: 0,
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.results.len() >= self.total
+    }
+
+    pub fn scroll_up(&mut self, amount: usize) {
+        self.scroll = self.scroll.saturating_sub(amount);
+    }
+
+    pub fn scroll_down(&mut self, amount: usize) {
+        self.scroll = (self.scroll + amount).min(self.results.len().saturating_sub(1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUITE: &str = "
+servers: [home]
+tests:
+  - domain: example.com
+    expect: resolved
+  - domain: doubleclick.net
+    expect: blocked
+    reason: ads
+  - name: intranet
+    domain: intranet.corp
+    answer: 10.0.0.5
+    via: both
+";
+
+    fn response(response_type: &str, reason: &str, response: &str) -> DNSResponse {
+        DNSResponse {
+            reason: reason.to_string(),
+            response: response.to_string(),
+            response_type: response_type.to_string(),
+            return_code: "NOERROR".to_string(),
+        }
+    }
+
+    fn case(server: &str, name: &str, verdict: Verdict) -> CaseResult {
+        CaseResult {
+            server: server.to_string(),
+            name: name.to_string(),
+            route: Route::Api,
+            verdict,
+            duration: Duration::from_millis(12),
+        }
+    }
+
+    #[test]
+    fn test_parse_suite() -> Result<()> {
+        let suite = Suite::parse(SUITE)?;
+        assert_eq!(suite.servers, ["home"]);
+        assert_eq!(suite.checks(), 4);
+        let labels: Vec<_> = suite.tests.iter().map(Expectation::label).collect();
+        assert_eq!(
+            labels,
+            [
+                "example.com A must resolve",
+                "doubleclick.net A must be BLOCKED with reason ads",
+                "intranet",
+            ]
+        );
+        assert_eq!(suite.tests[2].query_type, "A");
+
+        assert!(Suite::parse("tests: []").is_err());
+        assert!(Suite::parse("tests: [{domain: example.com}]").is_err());
+        assert!(Suite::parse("tests: [{domain: example.com, expect: resolvd}]").is_err());
+        assert!(Suite::parse("tests: [{domain: ads.net, reason: ads, via: dns}]").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_api_responses() -> Result<()> {
+        let suite = Suite::parse(SUITE)?;
+        let blocked = response("BLOCKED", "BLOCKED (Ads)", "A (0.0.0.0)");
+        let resolved = response(
+            "RESOLVED",
+            "RESOLVED (upstream)",
+            "A (10.0.0.5), A (10.0.0.6)",
+        );
+
+        assert_eq!(suite.tests[0].check_response(&resolved), Ok(()));
+        assert_eq!(
+            suite.tests[0].check_response(&blocked),
+            Err("expected RESOLVED, got BLOCKED (BLOCKED (Ads))".to_string())
+        );
+        assert_eq!(suite.tests[1].check_response(&blocked), Ok(()));
+        let mut other_group = blocked.clone();
+        other_group.reason = "BLOCKED (malware)".to_string();
+        assert!(suite.tests[1].check_response(&other_group).is_err());
+
+        assert_eq!(suite.tests[2].check_response(&resolved), Ok(()));
+        let mut similar = resolved.clone();
+        similar.response = "A (10.0.0.50)".to_string();
+        assert!(suite.tests[2].check_response(&similar).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_dns_answers() -> Result<()> {
+        let suite = Suite::parse(SUITE)?;
+        let answer = |return_code: &str, records: &[&str]| DnsAnswer {
+            return_code: return_code.to_string(),
+            records: records.iter().map(|r| r.to_string()).collect(),
+        };
+        assert_eq!(
+            suite.tests[0].check_answer(&answer("NOERROR", &["192.0.2.1"])),
+            Ok(())
+        );
+        assert_eq!(
+            suite.tests[0].check_answer(&answer("NOERROR", &["0.0.0.0"])),
+            Err("expected RESOLVED, got NOERROR [0.0.0.0]".to_string())
+        );
+        assert!(suite.tests[0]
+            .check_answer(&answer("NXDOMAIN", &[]))
+            .is_err());
+        assert_eq!(
+            suite.tests[2].check_answer(&answer("NOERROR", &["10.0.0.5"])),
+            Ok(())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reports() {
+        let results = [
+            case("home", "example.com A must resolve", Verdict::Pass),
+            case(
+                "home",
+                "a & b",
+                Verdict::Fail("expected RESOLVED, got \"BLOCKED\"".to_string()),
+            ),
+            case(
+                "office",
+                "example.com A must resolve",
+                Verdict::Error("timeout".to_string()),
+            ),
+        ];
+        assert_eq!(
+            summary_line(&results),
+            "3 checks: 1 passed, 1 failed, 1 errors"
+        );
+
+        let tap = report(&results, ReportFormat::Tap);
+        let lines: Vec<_> = tap.lines().collect();
+        assert_eq!(
+            lines[..3],
+            [
+                "TAP version 13",
+                "1..3",
+                "ok 1 - [home] example.com A must resolve (api)"
+            ]
+        );
+        assert_eq!(lines[3], "not ok 2 - [home] a & b (api)");
+        assert_eq!(
+            lines[5],
+            "  message: \"expected RESOLVED, got \\\"BLOCKED\\\"\""
+        );
+        assert_eq!(lines[6], "  severity: fail");
+
+        let junit = report(&results, ReportFormat::Junit);
+        assert!(junit.contains(
+            "<testsuites name=\"blocky-tui\" tests=\"3\" failures=\"1\" errors=\"1\" time=\"0.036\">"
+        ));
+        assert!(junit.contains("<testsuite name=\"home\" tests=\"2\" failures=\"1\" errors=\"0\""));
+        assert!(junit.contains(
+            "<testcase name=\"a &amp; b (api)\" classname=\"home\" time=\"0.012\">\n      \
+             <failure message=\"expected RESOLVED, got &quot;BLOCKED&quot;\"/>"
+        ));
+        assert!(junit.contains("<error message=\"timeout\"/>"));
+
+        assert_eq!(
+            ReportFormat::from_path(Path::new("report.XML")),
+            ReportFormat::Junit
+        );
+    }
+}
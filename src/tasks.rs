@@ -0,0 +1,224 @@
+use std::{
+    collections::BTreeMap,
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::mpsc::UnboundedSender;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::action::Action;
+
+/// Kinds of background operations, at most one operation of each kind is in flight
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum TaskKind {
+    DNSStatus,
+    BlockingStatus,
+    SetBlocking,
+    RefreshLists,
+    ClearCache,
+    DNSQuery,
+    BulkTest,
+    Suite,
+    Watchlist,
+}
+
+impl TaskKind {
+    /// Whether a new operation replaces the one in flight instead of being refused
+    ///
+    /// Only the latest query or blocking change is of interest, all other operations would
+    /// just repeat the running request.
+    fn replaces_running(&self) -> bool {
+        matches!(self, TaskKind::SetBlocking | TaskKind::DNSQuery)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TaskKind::DNSStatus => "probing DNS status",
+            TaskKind::BlockingStatus => "querying blocking status",
+            TaskKind::SetBlocking => "changing blocking",
+            TaskKind::RefreshLists => "refreshing lists",
+            TaskKind::ClearCache => "clearing cache",
+            TaskKind::DNSQuery => "querying DNS",
+            TaskKind::BulkTest => "testing domains",
+            TaskKind::Suite => "running test suite",
+            TaskKind::Watchlist => "checking watchlist",
+        }
+    }
+}
+
+/// Sends actions from a background task, they are dropped once the app has quit
+#[derive(Debug, Clone)]
+pub struct ActionSender(UnboundedSender<Action>);
+
+impl ActionSender {
+    pub fn send(&self, action: Action) {
+        if self.0.send(action).is_err() {
+            debug!("app is shut down, dropping action of background task");
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RunningTask {
+    id: u64,
+    started: Instant,
+    cancellation_token: CancellationToken,
+}
+
+/// Tracks the background operations of the app
+///
+/// Every task reports `Action::TaskFinished` when it is done or was cancelled.
+#[derive(Debug)]
+pub struct TaskManager {
+    action_tx: UnboundedSender<Action>,
+    /// parent of the tokens of all tasks, cancelled on quit
+    cancellation_token: CancellationToken,
+    running: BTreeMap<TaskKind, RunningTask>,
+    next_id: u64,
+}
+
+impl TaskManager {
+    pub fn new(action_tx: UnboundedSender<Action>) -> Self {
+        Self {
+            action_tx,
+            cancellation_token: CancellationToken::new(),
+            running: BTreeMap::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Spawns the task unless one of the same kind is in flight, returns whether it was spawned
+    ///
+    /// For kinds which replace the running task, the running one is cancelled instead.
+    pub fn spawn<F, Fut>(&mut self, kind: TaskKind, task: F) -> bool
+    where
+        F: FnOnce(ActionSender) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        if let Some(running) = self.running.get(&kind) {
+            if !kind.replaces_running() {
+                debug!("{kind:?} is already in flight, ignoring the new request");
+                return false;
+            }
+            debug!("cancelling {kind:?} task {}", running.id);
+            running.cancellation_token.cancel();
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let cancellation_token = self.cancellation_token.child_token();
+        self.running.insert(
+            kind,
+            RunningTask {
+                id,
+                started: Instant::now(),
+                cancellation_token: cancellation_token.clone(),
+            },
+        );
+
+        let future = task(ActionSender(self.action_tx.clone()));
+        let tx = ActionSender(self.action_tx.clone());
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => debug!("{kind:?} task {id} was cancelled"),
+                _ = future => {}
+            }
+            tx.send(Action::TaskFinished(kind, id));
+        });
+        true
+    }
+
+    /// Forgets the finished task, unless it was already replaced by a newer one
+    pub fn finish(&mut self, kind: TaskKind, id: u64) {
+        if self.running.get(&kind).is_some_and(|task| task.id == id) {
+            let task = self.running.remove(&kind).unwrap();
+            debug!(
+                "{kind:?} task {id} finished after {:?}",
+                task.started.elapsed()
+            );
+        }
+    }
+
+    pub fn is_running(&self, kind: TaskKind) -> bool {
+        self.running.contains_key(&kind)
+    }
+
+    /// Kinds of all tasks in flight
+    pub fn running(&self) -> impl Iterator<Item = TaskKind> + '_ {
+        self.running.keys().copied()
+    }
+
+    /// Time since the task of this kind was started, `None` if none is in flight
+    pub fn elapsed(&self, kind: TaskKind) -> Option<Duration> {
+        self.running.get(&kind).map(|task| task.started.elapsed())
+    }
+
+    /// Time since the oldest task in flight was started
+    pub fn longest_elapsed(&self) -> Option<Duration> {
+        self.running
+            .values()
+            .map(|task| task.started.elapsed())
+            .max()
+    }
+
+    /// Cancels all tasks in flight, e.g. when quitting
+    pub fn cancel_all(&mut self) {
+        self.cancellation_token.cancel();
+        self.running.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_duplicates_are_refused_or_replace_the_running_task() {
+        let (tx, mut rx) = unbounded_channel();
+        let mut tasks = TaskManager::new(tx);
+        let pending = |_| std::future::pending();
+
+        assert!(tasks.spawn(TaskKind::RefreshLists, pending));
+        assert!(!tasks.spawn(TaskKind::RefreshLists, pending));
+
+        assert!(tasks.spawn(TaskKind::DNSQuery, pending));
+        assert!(tasks.spawn(TaskKind::DNSQuery, pending));
+        // the replaced query reports that it finished, but the new one is still running
+        let Some(Action::TaskFinished(kind, id)) = rx.recv().await else {
+            panic!("replaced task did not finish");
+        };
+        tasks.finish(kind, id);
+        assert!(tasks.is_running(TaskKind::DNSQuery));
+        assert_eq!(
+            tasks.running().collect::<Vec<_>>(),
+            [TaskKind::RefreshLists, TaskKind::DNSQuery]
+        );
+
+        tasks.cancel_all();
+        assert_eq!(tasks.running().count(), 0);
+        for _ in 0..2 {
+            let action = tokio::time::timeout(Duration::from_secs(1), rx.recv()).await;
+            assert!(matches!(action, Ok(Some(Action::TaskFinished(..)))));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_finished_task_can_run_again() {
+        let (tx, mut rx) = unbounded_channel();
+        let mut tasks = TaskManager::new(tx);
+        tasks.spawn(TaskKind::ClearCache, |tx| async move {
+            tx.send(Action::Render);
+        });
+        assert_eq!(rx.recv().await, Some(Action::Render));
+        let Some(Action::TaskFinished(kind, id)) = rx.recv().await else {
+            panic!("task did not finish");
+        };
+        tasks.finish(kind, id);
+        assert!(!tasks.is_running(TaskKind::ClearCache));
+        assert!(tasks.spawn(TaskKind::ClearCache, |_| async {}));
+    }
+}
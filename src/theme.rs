@@ -0,0 +1,134 @@
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+/// Named color palettes which can be selected in the config
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    #[serde(alias = "high-contrast")]
+    HighContrast,
+    /// avoids red/green as the only distinction between states
+    #[serde(alias = "colorblind-safe")]
+    ColorblindSafe,
+    /// no colors at all, states are distinguished by text attributes
+    Monochrome,
+}
+
+impl ThemeName {
+    /// The configured theme, unless `NO_COLOR` is set which always selects the monochrome theme
+    ///
+    /// See <https://no-color.org>
+    pub fn resolve(self, no_color: Option<&str>) -> Self {
+        match no_color {
+            Some(value) if !value.is_empty() => ThemeName::Monochrome,
+            _ => self,
+        }
+    }
+}
+
+/// Semantic styles used by all render functions instead of hardcoded colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub name: ThemeName,
+    /// healthy, successful or enabled states
+    pub ok: Style,
+    /// pending, unknown or degraded states
+    pub warn: Style,
+    /// failed or unreachable states
+    pub error: Style,
+    /// focused tiles, key hints and active inputs
+    pub focus: Style,
+    /// secondary information like timestamps and descriptions
+    pub muted: Style,
+    /// regular text
+    pub text: Style,
+    /// the selected entry of a list or the selected button
+    pub selected: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::new(ThemeName::Default)
+    }
+}
+
+impl Theme {
+    pub fn new(name: ThemeName) -> Self {
+        let fg = |color| Style::default().fg(color);
+        match name {
+            ThemeName::Default => Self {
+                name,
+                ok: fg(Color::Green),
+                warn: fg(Color::Yellow),
+                error: fg(Color::Red),
+                focus: fg(Color::Yellow),
+                muted: fg(Color::DarkGray),
+                text: fg(Color::White),
+                selected: fg(Color::Black).bg(Color::Yellow),
+            },
+            ThemeName::HighContrast => Self {
+                name,
+                ok: fg(Color::LightGreen).add_modifier(Modifier::BOLD),
+                warn: fg(Color::LightYellow).add_modifier(Modifier::BOLD),
+                error: fg(Color::LightRed).add_modifier(Modifier::BOLD),
+                focus: fg(Color::LightCyan).add_modifier(Modifier::BOLD),
+                muted: fg(Color::Gray),
+                text: fg(Color::White),
+                selected: fg(Color::Black).bg(Color::White),
+            },
+            // colors of the Okabe-Ito palette, which are distinguishable with all common color vision deficiencies
+            ThemeName::ColorblindSafe => Self {
+                name,
+                ok: fg(Color::Rgb(0, 114, 178)),
+                warn: fg(Color::Rgb(230, 159, 0)),
+                error: fg(Color::Rgb(213, 94, 0)),
+                focus: fg(Color::Rgb(86, 180, 233)),
+                muted: fg(Color::Gray),
+                text: fg(Color::White),
+                selected: fg(Color::Black).bg(Color::Rgb(86, 180, 233)),
+            },
+            ThemeName::Monochrome => Self {
+                name,
+                ok: Style::default(),
+                warn: Style::default().add_modifier(Modifier::ITALIC),
+                error: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                focus: Style::default().add_modifier(Modifier::BOLD),
+                muted: Style::default().add_modifier(Modifier::DIM),
+                text: Style::default(),
+                selected: Style::default().add_modifier(Modifier::REVERSED),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_color_selects_monochrome() {
+        let name = ThemeName::HighContrast;
+        assert_eq!(name.resolve(None), ThemeName::HighContrast);
+        assert_eq!(name.resolve(Some("")), ThemeName::HighContrast);
+        assert_eq!(name.resolve(Some("1")), ThemeName::Monochrome);
+    }
+
+    #[test]
+    fn test_monochrome_has_no_colors() {
+        let theme = Theme::new(ThemeName::Monochrome);
+        for style in [
+            theme.ok,
+            theme.warn,
+            theme.error,
+            theme.focus,
+            theme.muted,
+            theme.text,
+            theme.selected,
+        ] {
+            assert_eq!(style.fg, None);
+            assert_eq!(style.bg, None);
+        }
+    }
+}
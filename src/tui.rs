@@ -1,4 +1,5 @@
 use std::{
+    io::Stdout,
     ops::{Deref, DerefMut},
     time::Duration,
 };
@@ -12,16 +13,20 @@ use crossterm::{
     terminal::{EnterAlternateScreen, LeaveAlternateScreen},
 };
 
-use futures::{FutureExt, StreamExt};
-use ratatui::backend::CrosstermBackend as Backend;
+use futures::{stream::BoxStream, FutureExt, StreamExt};
+use ratatui::backend::{Backend, CrosstermBackend};
 use tokio::{
     sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
     task::JoinHandle,
+    time::timeout,
 };
 use tokio_util::sync::CancellationToken;
 
 use anyhow::Result;
-use tracing::{debug, info};
+use tracing::{debug, error, info, warn};
+
+/// Time the event task gets to notice the cancellation before it is aborted
+const EVENT_TASK_TIMEOUT: Duration = Duration::from_millis(200);
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Event {
@@ -38,8 +43,71 @@ pub enum Event {
     Resize(u16, u16),
 }
 
-pub struct Tui {
-    pub terminal: ratatui::Terminal<Backend<std::io::Stdout>>,
+/// The terminal the TUI runs in, apart from drawing which is done by the ratatui backend
+///
+/// Kept separate so the setup and teardown can be tested without a real terminal.
+pub trait TerminalDevice: Send {
+    /// Switches to raw mode and the alternate screen
+    fn enter(&mut self, mouse: bool, paste: bool) -> Result<()>;
+    /// Switches back to the shell's terminal, must be safe to call in any state
+    fn leave(&mut self, mouse: bool, paste: bool) -> Result<()>;
+    /// Input events of the terminal
+    fn events(&mut self) -> BoxStream<'static, std::io::Result<CrosstermEvent>>;
+}
+
+/// The terminal on stdout
+pub struct Crossterm;
+
+impl TerminalDevice for Crossterm {
+    fn enter(&mut self, mouse: bool, paste: bool) -> Result<()> {
+        crossterm::terminal::enable_raw_mode()?;
+        crossterm::execute!(std::io::stdout(), EnterAlternateScreen, cursor::Hide)?;
+        if mouse {
+            crossterm::execute!(std::io::stdout(), EnableMouseCapture)?;
+        }
+        if paste {
+            crossterm::execute!(std::io::stdout(), EnableBracketedPaste)?;
+        }
+        Ok(())
+    }
+
+    fn leave(&mut self, mouse: bool, paste: bool) -> Result<()> {
+        restore_terminal(mouse, paste)
+    }
+
+    fn events(&mut self) -> BoxStream<'static, std::io::Result<CrosstermEvent>> {
+        crossterm::event::EventStream::new().boxed()
+    }
+}
+
+/// Gives the terminal on stdout back to the shell, e.g. from the panic hook
+///
+/// Every step is run even if an earlier one failed, the first error is returned.
+pub fn restore_terminal(mouse: bool, paste: bool) -> Result<()> {
+    let mut stdout = std::io::stdout();
+    let steps = [
+        if paste {
+            crossterm::execute!(stdout, DisableBracketedPaste)
+        } else {
+            Ok(())
+        },
+        if mouse {
+            crossterm::execute!(stdout, DisableMouseCapture)
+        } else {
+            Ok(())
+        },
+        crossterm::execute!(stdout, LeaveAlternateScreen, cursor::Show),
+        crossterm::terminal::disable_raw_mode(),
+    ];
+    match steps.into_iter().find_map(Result::err) {
+        Some(err) => Err(err.into()),
+        None => Ok(()),
+    }
+}
+
+pub struct Tui<B: Backend = CrosstermBackend<Stdout>> {
+    pub terminal: ratatui::Terminal<B>,
+    pub device: Box<dyn TerminalDevice>,
     pub task: JoinHandle<()>,
     pub cancellation_token: CancellationToken,
     pub event_rx: UnboundedReceiver<Event>,
@@ -47,12 +115,23 @@ pub struct Tui {
     pub frame_rate: f64,
     pub mouse: bool,
     pub paste: bool,
+    /// whether the terminal was entered and has to be restored
+    active: bool,
 }
 
 impl Tui {
     pub fn new() -> Result<Self> {
+        Self::with_backend(
+            CrosstermBackend::new(std::io::stdout()),
+            Box::new(Crossterm),
+        )
+    }
+}
+
+impl<B: Backend> Tui<B> {
+    pub fn with_backend(backend: B, device: Box<dyn TerminalDevice>) -> Result<Self> {
         let frame_rate = 10.0;
-        let terminal = ratatui::Terminal::new(Backend::new(std::io::stdout()))?;
+        let terminal = ratatui::Terminal::new(backend)?;
         let (event_tx, event_rx) = mpsc::unbounded_channel();
         let cancellation_token = CancellationToken::new();
         let task = tokio::spawn(async {});
@@ -61,6 +140,7 @@ impl Tui {
         debug!("created new TUI struct");
         Ok(Self {
             terminal,
+            device,
             task,
             cancellation_token,
             event_rx,
@@ -68,6 +148,7 @@ impl Tui {
             frame_rate,
             mouse,
             paste,
+            active: false,
         })
     }
     pub fn frame_rate(mut self, frame_rate: f64) -> Self {
@@ -90,14 +171,20 @@ impl Tui {
         self.cancellation_token = CancellationToken::new();
         let _cancellation_token = self.cancellation_token.clone();
         let _event_tx = self.event_tx.clone();
+        let mut reader = self.device.events();
         self.task = tokio::spawn(async move {
-            let mut reader = crossterm::event::EventStream::new();
             let mut render_interval = tokio::time::interval(render_delay);
-            _event_tx.send(Event::Init).unwrap();
+            let mut event = Some(Event::Init);
             loop {
+                // the app may already be gone when the task is cancelled, so send errors end the task
+                if let Some(event) = event.take() {
+                    if _event_tx.send(event).is_err() {
+                        break;
+                    }
+                }
                 let render_delay = render_interval.tick();
                 let crossterm_event = reader.next().fuse();
-                tokio::select! {
+                event = tokio::select! {
                     _ = _cancellation_token.cancelled() => {
                         break;
                     }
@@ -105,86 +192,84 @@ impl Tui {
                         match maybe_event {
                             Some(Ok(evt)) => {
                                 match evt {
-                                    CrosstermEvent::Key(key) => {
-                                        if key.kind == KeyEventKind::Press {
-                                            _event_tx.send(Event::Key(key)).unwrap();
-                                        }
+                                    CrosstermEvent::Key(key) if key.kind == KeyEventKind::Press => {
+                                        Some(Event::Key(key))
                                     }
-                                    CrosstermEvent::Mouse(mouse) => {
-                                        _event_tx.send(Event::Mouse(mouse)).unwrap();
-                                    },
-                                    CrosstermEvent::Resize(x, y) => {
-                                        _event_tx.send(Event::Resize(x, y)).unwrap();
-                                    },
-                                    CrosstermEvent::FocusLost => {
-                                        _event_tx.send(Event::FocusLost).unwrap();
-                                    },
-                                    CrosstermEvent::FocusGained => {
-                                        _event_tx.send(Event::FocusGained).unwrap();
-                                    },
-                                    CrosstermEvent::Paste(s) => {
-                                        _event_tx.send(Event::Paste(s)).unwrap();
-                                    },
+                                    CrosstermEvent::Key(_) => None,
+                                    CrosstermEvent::Mouse(mouse) => Some(Event::Mouse(mouse)),
+                                    CrosstermEvent::Resize(x, y) => Some(Event::Resize(x, y)),
+                                    CrosstermEvent::FocusLost => Some(Event::FocusLost),
+                                    CrosstermEvent::FocusGained => Some(Event::FocusGained),
+                                    CrosstermEvent::Paste(s) => Some(Event::Paste(s)),
                                 }
                             }
-                            Some(Err(_)) => {
-                                 _event_tx.send(Event::Error).unwrap();
+                            Some(Err(_)) => Some(Event::Error),
+                            None => {
+                                // the input is gone, polling it again would spin
+                                let _ = _event_tx.send(Event::Closed);
+                                break;
                             }
-                            None => {},
                         }
                     },
-                    _ = render_delay => {
-                        _event_tx.send(Event::Render).unwrap();
-                    },
-                }
+                    _ = render_delay => Some(Event::Render),
+                };
             }
+            debug!("stopped listening for tui events");
         });
         info!("started listening for tui events");
     }
 
-    pub fn stop(&self) -> Result<()> {
+    /// Cancels the event task and waits until it is done, it is aborted if it takes too long
+    pub async fn stop(&mut self) {
         self.cancel();
-        let mut counter = 0;
-        while !self.task.is_finished() {
-            std::thread::sleep(Duration::from_millis(1));
-            counter += 1;
-            if counter > 50 {
+        match timeout(EVENT_TASK_TIMEOUT, &mut self.task).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => warn!("event task failed: {err}"),
+            Err(_) => {
+                warn!("event task did not stop within {EVENT_TASK_TIMEOUT:?}, aborting it");
                 self.task.abort();
             }
-            if counter > 100 {
-                tracing::error!("Failed to abort task in 100 milliseconds for unknown reason");
-                break;
-            }
         }
-        Ok(())
     }
 
     pub fn enter(&mut self) -> Result<()> {
-        crossterm::terminal::enable_raw_mode()?;
-        crossterm::execute!(std::io::stdout(), EnterAlternateScreen, cursor::Hide)?;
-        if self.mouse {
-            crossterm::execute!(std::io::stdout(), EnableMouseCapture)?;
-        }
-        if self.paste {
-            crossterm::execute!(std::io::stdout(), EnableBracketedPaste)?;
-        }
+        // set first, a partially entered terminal has to be restored as well
+        self.active = true;
+        self.device.enter(self.mouse, self.paste)?;
         self.start();
         Ok(())
     }
 
-    pub fn exit(&mut self) -> Result<()> {
-        self.stop()?;
-        if crossterm::terminal::is_raw_mode_enabled()? {
-            self.flush()?;
-            if self.paste {
-                crossterm::execute!(std::io::stdout(), DisableBracketedPaste)?;
-            }
-            if self.mouse {
-                crossterm::execute!(std::io::stdout(), DisableMouseCapture)?;
-            }
-            crossterm::execute!(std::io::stdout(), LeaveAlternateScreen, cursor::Show)?;
-            crossterm::terminal::disable_raw_mode()?;
+    /// Stops the event task and restores the terminal, does nothing if it is not entered
+    ///
+    /// The terminal is restored even if flushing the last frame fails.
+    pub async fn exit(&mut self) -> Result<()> {
+        if !self.active {
+            return Ok(());
         }
+        self.stop().await;
+        let flushed = self.terminal.flush();
+        let restored = self.device.leave(self.mouse, self.paste);
+        self.active = false;
+        flushed?;
+        restored
+    }
+
+    /// Restores the terminal and stops the process like Ctrl-Z in a shell, returns after `fg`
+    ///
+    /// Raw mode disables the terminal's own handling of Ctrl-Z, so the signal is raised here.
+    pub async fn suspend(&mut self) -> Result<()> {
+        self.exit().await?;
+        #[cfg(unix)]
+        signal_hook::low_level::raise(signal_hook::consts::signal::SIGTSTP)?;
+        Ok(())
+    }
+
+    /// Takes the terminal over again after `suspend` or an external program
+    pub fn resume(&mut self) -> Result<()> {
+        self.enter()?;
+        // the screen content is unknown, so the next frame has to draw everything
+        self.terminal.clear()?;
         Ok(())
     }
 
@@ -197,22 +282,150 @@ impl Tui {
     }
 }
 
-impl Deref for Tui {
-    type Target = ratatui::Terminal<Backend<std::io::Stdout>>;
+impl<B: Backend> Deref for Tui<B> {
+    type Target = ratatui::Terminal<B>;
 
     fn deref(&self) -> &Self::Target {
         &self.terminal
     }
 }
 
-impl DerefMut for Tui {
+impl<B: Backend> DerefMut for Tui<B> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.terminal
     }
 }
 
-impl Drop for Tui {
+impl<B: Backend> Drop for Tui<B> {
+    /// Last resort if `exit` was not reached, e.g. on an early return or a panic
+    ///
+    /// Nothing can be awaited here, so the event task is aborted and errors are only logged.
     fn drop(&mut self) {
-        self.exit().unwrap();
+        if !self.active {
+            return;
+        }
+        self.cancel();
+        self.task.abort();
+        if let Err(err) = self.device.leave(self.mouse, self.paste) {
+            error!("could not restore the terminal: {err:?}");
+        }
+        self.active = false;
+    }
+}
+
+/// A terminal which records the calls of the TUI instead of changing a real terminal
+#[cfg(test)]
+pub mod fake {
+    use std::sync::{Arc, Mutex};
+
+    use anyhow::bail;
+    use ratatui::backend::TestBackend;
+
+    use super::*;
+
+    #[derive(Debug, Default, Clone)]
+    pub struct FakeDevice {
+        pub calls: Arc<Mutex<Vec<&'static str>>>,
+        pub fail_leave: bool,
+        /// the input ends right away like a closed terminal
+        pub closed_input: bool,
+    }
+
+    impl FakeDevice {
+        pub fn calls(&self) -> Vec<&'static str> {
+            self.calls.lock().unwrap().clone()
+        }
+
+        pub fn tui(&self) -> Tui<TestBackend> {
+            Tui::with_backend(TestBackend::new(80, 24), Box::new(self.clone())).unwrap()
+        }
+    }
+
+    impl TerminalDevice for FakeDevice {
+        fn enter(&mut self, _mouse: bool, _paste: bool) -> Result<()> {
+            self.calls.lock().unwrap().push("enter");
+            Ok(())
+        }
+
+        fn leave(&mut self, _mouse: bool, _paste: bool) -> Result<()> {
+            self.calls.lock().unwrap().push("leave");
+            if self.fail_leave {
+                bail!("terminal is gone");
+            }
+            Ok(())
+        }
+
+        fn events(&mut self) -> BoxStream<'static, std::io::Result<CrosstermEvent>> {
+            if self.closed_input {
+                futures::stream::empty().boxed()
+            } else {
+                futures::stream::pending().boxed()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fake::FakeDevice, *};
+
+    #[tokio::test]
+    async fn test_exit_stops_the_event_task_and_is_idempotent() -> Result<()> {
+        let device = FakeDevice::default();
+        let mut tui = device.tui();
+        // nothing to restore before the terminal was entered
+        tui.exit().await?;
+        assert!(device.calls().is_empty());
+
+        tui.enter()?;
+        assert_eq!(tui.next().await, Some(Event::Init));
+        tui.exit().await?;
+        assert!(tui.task.is_finished());
+        tui.exit().await?;
+        drop(tui);
+        assert_eq!(device.calls(), ["enter", "leave"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stuck_event_task_is_aborted() -> Result<()> {
+        let device = FakeDevice::default();
+        let mut tui = device.tui();
+        tui.enter()?;
+        tui.task.abort();
+        tui.task = tokio::spawn(std::future::pending());
+        tokio::time::timeout(EVENT_TASK_TIMEOUT * 2, tui.exit()).await??;
+        assert_eq!(device.calls(), ["enter", "leave"]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failed_restore_is_reported_once() {
+        let device = FakeDevice {
+            fail_leave: true,
+            ..Default::default()
+        };
+        let mut tui = device.tui();
+        tui.enter().unwrap();
+        assert!(tui.exit().await.is_err());
+        assert!(tui.exit().await.is_ok());
+        drop(tui);
+        assert_eq!(device.calls(), ["enter", "leave"]);
+    }
+
+    #[tokio::test]
+    async fn test_drop_restores_the_terminal() {
+        let device = FakeDevice {
+            fail_leave: true,
+            ..Default::default()
+        };
+        let mut tui = device.tui();
+        tui.enter().unwrap();
+        let task = tui.task.abort_handle();
+        // must not panic although restoring fails
+        drop(tui);
+        assert_eq!(device.calls(), ["enter", "leave"]);
+        tokio::task::yield_now().await;
+        assert!(task.is_finished());
     }
 }
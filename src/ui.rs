@@ -1,34 +1,136 @@
 use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout, Rect},
-    style::{Color, Style, Stylize},
+    style::{Style, Stylize},
     text::{Line, Span, Text},
-    widgets::{Block, BorderType, Borders, Paragraph, Wrap},
+    widgets::{Block, BorderType, Borders, Clear, Paragraph, Row, Table, Wrap},
     Frame,
 };
 
 use crate::{
-    app::{ActionState, ApiQueryResponseState, App, CurrentFocus},
-    port_check::PortState,
+    app::{ActionState, ApiQueryResponseState, App, CurrentFocus, CurrentScreen, DNSQueryState},
+    bulk::summarize,
+    logging::{utc_time_label, LOG_BUFFER},
+    notification::{Notification, NotificationLevel},
+    palette::{CommandPalette, MAX_PALETTE_MATCHES},
+    port_check::{AddressProbe, PortState},
+    suite::{self, Verdict},
+    tasks::TaskKind,
+    transport::Transport,
+    watchlist::Watch,
 };
+use std::time::Duration;
+use tracing::Level;
 
-impl App {
-    pub fn render(&self, frame: &mut Frame) {
+/// Minimum terminal size, smaller terminals only show a hint to enlarge the terminal
+pub const MIN_WIDTH: u16 = 60;
+/// the sum of the rows of the compact layout, its tiles would be clipped otherwise
+pub const MIN_HEIGHT: u16 = 22;
+/// Terminals smaller than this use the compact layout
+pub const STANDARD_MIN_WIDTH: u16 = 100;
+pub const STANDARD_MIN_HEIGHT: u16 = 30;
+/// Terminals at least this wide show the event log next to the tiles
+pub const WIDE_MIN_WIDTH: u16 = 160;
+
+/// Frames of the spinner shown for operations in flight
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_FRAME_DURATION: Duration = Duration::from_millis(100);
+
+/// Spinner frame of an operation running for `elapsed`
+pub fn spinner_frame(elapsed: Duration) -> &'static str {
+    let frame = elapsed.as_millis() / SPINNER_FRAME_DURATION.as_millis();
+    SPINNER_FRAMES[frame as usize % SPINNER_FRAMES.len()]
+}
+
+/// Arrangement of the tiles, chosen by the terminal size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutMode {
+    /// below [`MIN_WIDTH`] x [`MIN_HEIGHT`], nothing but a hint is rendered
+    TooSmall,
+    /// one line title and a two column grid, fits an 80x24 terminal
+    Compact,
+    /// title block, three tiles in the middle row and two tiles in the bottom row
+    Standard,
+    /// standard layout with the event log as side panel
+    Wide,
+}
+
+impl LayoutMode {
+    pub fn for_area(area: Rect) -> Self {
+        if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+            LayoutMode::TooSmall
+        } else if area.width < STANDARD_MIN_WIDTH || area.height < STANDARD_MIN_HEIGHT {
+            LayoutMode::Compact
+        } else if area.width < WIDE_MIN_WIDTH {
+            LayoutMode::Standard
+        } else {
+            LayoutMode::Wide
+        }
+    }
+}
+
+/// Areas of all layout components, shared between rendering and mouse hit-testing
+///
+/// Areas which are not part of the chosen layout mode are empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MainLayout {
+    pub mode: LayoutMode,
+    pub title: Rect,
+    /// area below the title, used by the event log and the log viewer
+    pub content: Rect,
+    pub dns_status: Rect,
+    pub blocking_status: Rect,
+    pub refresh_lists: Rect,
+    pub query: Rect,
+    pub delete_cache: Rect,
+    /// event log next to the tiles in the wide layout
+    pub side_panel: Rect,
+}
+
+impl MainLayout {
+    pub fn new(area: Rect) -> Self {
+        match LayoutMode::for_area(area) {
+            LayoutMode::TooSmall => Self {
+                mode: LayoutMode::TooSmall,
+                title: Rect::default(),
+                content: Rect::default(),
+                dns_status: Rect::default(),
+                blocking_status: Rect::default(),
+                refresh_lists: Rect::default(),
+                query: Rect::default(),
+                delete_cache: Rect::default(),
+                side_panel: Rect::default(),
+            },
+            LayoutMode::Compact => Self::compact(area),
+            LayoutMode::Standard => Self::standard(area, LayoutMode::Standard),
+            LayoutMode::Wide => {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+                    .split(area);
+                let mut layout = Self::standard(columns[0], LayoutMode::Wide);
+                layout.side_panel = columns[1];
+                layout
+            }
+        }
+    }
+
+    fn standard(area: Rect, mode: LayoutMode) -> Self {
         // tiles are the individual layout components
         let main_tiles = Layout::default()
             .direction(Direction::Vertical)
             .constraints(vec![
-                Constraint::Percentage(20), // Title Block
-                Constraint::Percentage(45), // Main Tiles
-                Constraint::Percentage(35), // Bottom Tile
+                Constraint::Length(3),      // Title Block
+                Constraint::Percentage(55), // Main Tiles
+                Constraint::Min(8),         // Bottom Tile
             ])
-            .split(frame.size());
+            .split(area);
 
         let mid_tiles = Layout::default()
             .direction(Direction::Horizontal)
             .constraints(vec![
-                Constraint::Percentage(33),
-                Constraint::Percentage(33),
-                Constraint::Percentage(33),
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
             ])
             .split(main_tiles[1]);
 
@@ -37,20 +139,197 @@ impl App {
             .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
             .split(main_tiles[2]);
 
-        self.render_title(main_tiles[0], frame);
+        Self {
+            mode,
+            title: main_tiles[0],
+            content: main_tiles[1].union(main_tiles[2]),
+            dns_status: mid_tiles[0],
+            blocking_status: mid_tiles[1],
+            refresh_lists: mid_tiles[2],
+            query: bottom_tiles[0],
+            delete_cache: bottom_tiles[1],
+            side_panel: Rect::default(),
+        }
+    }
+
+    /// Two tiles per row, the query tile spans the whole last row
+    fn compact(area: Rect) -> Self {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(vec![
+                Constraint::Length(1), // Title Line
+                Constraint::Min(10),   // Status Tiles
+                Constraint::Length(5), // Action Tiles
+                Constraint::Length(6), // Query Tile
+            ])
+            .split(area);
+        let columns = |r: Rect| {
+            Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(r)
+        };
+        let status_row = columns(rows[1]);
+        let action_row = columns(rows[2]);
+
+        Self {
+            mode: LayoutMode::Compact,
+            title: rows[0],
+            content: rows[1].union(rows[3]),
+            dns_status: status_row[0],
+            blocking_status: status_row[1],
+            refresh_lists: action_row[0],
+            delete_cache: action_row[1],
+            query: rows[3],
+            side_panel: Rect::default(),
+        }
+    }
+
+    pub fn tile_area(&self, tile: &CurrentFocus) -> Rect {
+        match tile {
+            CurrentFocus::DNSStatus => self.dns_status,
+            CurrentFocus::BlockingStatus => self.blocking_status,
+            CurrentFocus::RefreshLists => self.refresh_lists,
+            CurrentFocus::DeleteCache => self.delete_cache,
+            CurrentFocus::QueryDNS => self.query,
+        }
+    }
+
+    /// The tile at the given terminal cell, if any
+    pub fn tile_at(&self, x: u16, y: u16) -> Option<CurrentFocus> {
+        [
+            CurrentFocus::DNSStatus,
+            CurrentFocus::BlockingStatus,
+            CurrentFocus::RefreshLists,
+            CurrentFocus::DeleteCache,
+            CurrentFocus::QueryDNS,
+        ]
+        .into_iter()
+        .find(|tile| contains(self.tile_area(tile), x, y))
+    }
+}
+
+pub fn contains(r: Rect, x: u16, y: u16) -> bool {
+    x >= r.x && x < r.right() && y >= r.y && y < r.bottom()
+}
+
+/// The clickable button in the last row inside of a tile's border
+pub fn button_area(tile_area: Rect, label: &str) -> Rect {
+    let inner = Block::default().borders(Borders::ALL).inner(tile_area);
+    if inner.height < 2 {
+        return Rect::default();
+    }
+    let width = (label.chars().count() as u16).min(inner.width);
+    Rect::new(
+        inner.x + (inner.width - width) / 2,
+        inner.bottom() - 1,
+        width,
+        1,
+    )
+}
+
+/// The inner area of a tile without the last row, which is reserved for the tile's button
+fn above_button(inner: Rect) -> Rect {
+    Rect {
+        height: inner.height.saturating_sub(1),
+        ..inner
+    }
+}
+
+/// The area of the command palette, the matches start in the third row
+pub fn palette_area(r: Rect, shown_matches: usize) -> Rect {
+    let width = 60.min(r.width);
+    let height = (shown_matches as u16 + 3).max(4).min(r.height);
+    Rect::new(r.x + (r.width - width) / 2, r.y + 1, width, height)
+}
+
+impl App {
+    pub fn render(&self, frame: &mut Frame) {
+        let layout = MainLayout::new(frame.size());
+        if layout.mode == LayoutMode::TooSmall {
+            self.render_too_small(frame.size(), frame);
+            return;
+        }
+
+        self.render_title(&layout, frame);
+
+        match self.current_screen {
+            CurrentScreen::EventLog => self.render_event_log(layout.content, frame),
+            CurrentScreen::Logs => self.render_log_view(layout.content, frame),
+            CurrentScreen::BulkTest => self.render_bulk_test(layout.content, frame),
+            CurrentScreen::Suite => self.render_suite(layout.content, frame),
+            _ => self.render_main_tiles(&layout, frame),
+        }
+        if layout.mode == LayoutMode::Wide && self.current_screen != CurrentScreen::EventLog {
+            self.render_event_log(layout.side_panel, frame);
+        }
+
+        if let Some(palette) = &self.palette {
+            self.render_palette(palette, frame.size(), frame);
+        }
+
+        if let Some(dialog) = &self.dialog {
+            frame.render_widget(dialog.widget(&self.theme), frame.size());
+        }
+
+        if self.show_help {
+            self.render_help(frame.size(), frame);
+        }
+
+        self.render_toasts(frame.size(), frame);
+    }
 
-        self.render_dns_status_tile(mid_tiles[0], frame);
-        self.render_blocking_status_tile(mid_tiles[1], frame);
-        self.render_refresh_list_tile(mid_tiles[2], frame);
+    fn render_main_tiles(&self, layout: &MainLayout, frame: &mut Frame) {
+        self.render_dns_status_tile(layout.dns_status, frame);
+        self.render_blocking_status_tile(layout.blocking_status, frame);
+        self.render_refresh_list_tile(layout.refresh_lists, frame);
 
-        self.render_query_tile(bottom_tiles[0], frame);
-        self.render_cache_delete_tile(bottom_tiles[1], frame);
+        self.render_query_tile(layout.query, frame);
+        self.render_cache_delete_tile(layout.delete_cache, frame);
+
+        for tile in [
+            CurrentFocus::DNSStatus,
+            CurrentFocus::BlockingStatus,
+            CurrentFocus::RefreshLists,
+            CurrentFocus::DeleteCache,
+        ] {
+            self.render_tile_button(&tile, layout.tile_area(&tile), frame);
+        }
+    }
+
+    /// Label of the clickable button of a tile, tiles without operation have no button
+    pub fn tile_button_label(&self, tile: &CurrentFocus) -> Option<&'static str> {
+        match tile {
+            CurrentFocus::DNSStatus => Some("[ Probe ]"),
+            CurrentFocus::BlockingStatus => match &self.blocking_status {
+                Some(status) if !status.is_blocking_enabled => Some("[ Enable ]"),
+                _ => Some("[ Disable ]"),
+            },
+            CurrentFocus::RefreshLists => Some("[ Refresh ]"),
+            CurrentFocus::DeleteCache => Some("[ Delete ]"),
+            CurrentFocus::QueryDNS => None,
+        }
+    }
+
+    fn render_tile_button(&self, tile: &CurrentFocus, r: Rect, frame: &mut Frame) {
+        let Some(label) = self.tile_button_label(tile) else {
+            return;
+        };
+        let style = if self.current_focus == *tile {
+            self.theme.focus.bold()
+        } else {
+            self.theme.text
+        };
+        frame.render_widget(
+            Paragraph::new(Line::styled(label, style)),
+            button_area(r, label),
+        );
     }
 
     fn render_dns_status_tile(&self, r: Rect, frame: &mut Frame) {
         let api_status_line = match self.dns_status.query_response_state {
             Some(ApiQueryResponseState::Healthy) => {
-                let marker = Span::styled("✓", Style::default().fg(Color::Green));
+                let marker = Span::styled("✓", self.theme.ok);
                 Line::from(vec![
                     "- [".into(),
                     marker,
@@ -58,7 +337,7 @@ impl App {
                 ])
             }
             Some(ApiQueryResponseState::Unhealthy) => {
-                let marker = Span::styled("🗙", Style::default().fg(Color::Red));
+                let marker = Span::styled("🗙", self.theme.error);
                 Line::from(vec![
                     "- [".into(),
                     marker,
@@ -66,7 +345,7 @@ impl App {
                 ])
             }
             Some(ApiQueryResponseState::NoResponse) => {
-                let marker = Span::styled("🗙", Style::default().fg(Color::Red));
+                let marker = Span::styled("🗙", self.theme.error);
                 Line::from(vec![
                     "- [".into(),
                     marker,
@@ -74,106 +353,178 @@ impl App {
                 ])
             }
             None => {
-                let marker = Span::styled("?", Style::default().fg(Color::Yellow));
+                let marker = Span::styled("?", self.theme.warn);
                 Line::from(vec!["- [".into(), marker, "] API not yet probed".into()])
             }
         };
 
+        let api_target = match &self.api.transport {
+            Transport::UnixSocket(path) => format!("unix:{}", path.display()),
+            _ => format!("tcp:{}", self.api.api_port),
+        };
         let tcp_port_line = match self.dns_status.tcp_port_state {
             Some(PortState::Open) => {
-                let marker = Span::styled("✓", Style::default().fg(Color::Green));
+                let marker = Span::styled("✓", self.theme.ok);
+                Line::from(vec![
+                    "- [".into(),
+                    marker,
+                    format!("] API port ({api_target}) is open").into(),
+                ])
+            }
+            Some(PortState::Partial) => {
+                let marker = Span::styled("!", self.theme.warn);
                 Line::from(vec![
                     "- [".into(),
                     marker,
-                    format!("] API port (tcp:{}) is open", self.api.api_port).into(),
+                    format!("] API port ({api_target}) is closed on some addresses").into(),
                 ])
             }
             Some(PortState::Closed) => {
-                let marker = Span::styled("🗙", Style::default().fg(Color::Red));
+                let marker = Span::styled("🗙", self.theme.error);
                 Line::from(vec![
                     "- [".into(),
                     marker,
-                    format!("] API port (tcp:{}) is closed", self.api.api_port).into(),
+                    format!("] API port ({api_target}) is closed").into(),
                 ])
             }
             Some(PortState::Error) => {
-                let marker = Span::styled("🗙", Style::default().fg(Color::Red));
+                let marker = Span::styled("🗙", self.theme.error);
                 Line::from(vec![
                     "- [".into(),
                     marker,
-                    format!("] error when probing API port (tcp:{})", self.api.api_port).into(),
+                    format!("] error when probing API port ({api_target})").into(),
                 ])
             }
             None => {
-                let marker = Span::styled("?", Style::default().fg(Color::Yellow));
+                let marker = Span::styled("?", self.theme.warn);
                 Line::from(vec![
                     "- [".into(),
                     marker,
-                    format!("] API port (tcp:{}) not yet probed", self.api.api_port).into(),
+                    format!("] API port ({api_target}) not yet probed").into(),
+                ])
+            }
+            Some(PortState::Skipped) => {
+                let marker = Span::styled("-", self.theme.muted);
+                let route = self.api.transport.label().unwrap_or_default();
+                Line::from(vec![
+                    "- [".into(),
+                    marker,
+                    Span::styled(
+                        format!("] API port probe skipped, requests go {route}"),
+                        self.theme.muted,
+                    ),
                 ])
             }
         };
 
+        // DNS is always queried directly, only the API requests are routed through a proxy
+        let udp_note = match self.api.transport {
+            Transport::Proxy(_) => ", not proxied",
+            _ => "",
+        };
         let udp_port_line = match self.dns_status.udp_port_state {
             Some(PortState::Open) => {
-                let marker = Span::styled("✓", Style::default().fg(Color::Green));
+                let marker = Span::styled("✓", self.theme.ok);
                 Line::from(vec![
                     "- [".into(),
                     marker,
                     format!(
-                        "] DNS port (udp:{}) is open and responding",
+                        "] DNS port (udp:{}{udp_note}) is open and responding",
+                        self.api.dns_port
+                    )
+                    .into(),
+                ])
+            }
+            Some(PortState::Partial) => {
+                let marker = Span::styled("!", self.theme.warn);
+                Line::from(vec![
+                    "- [".into(),
+                    marker,
+                    format!(
+                        "] DNS port (udp:{}{udp_note}) is not answering on some addresses",
                         self.api.dns_port
                     )
                     .into(),
                 ])
             }
             Some(PortState::Closed) => {
-                let marker = Span::styled("🗙", Style::default().fg(Color::Red));
+                let marker = Span::styled("🗙", self.theme.error);
                 Line::from(vec![
                     "- [".into(),
                     marker,
-                    format!("] DNS port (udp:{}) is not answering", self.api.dns_port).into(),
+                    format!(
+                        "] DNS port (udp:{}{udp_note}) is not answering",
+                        self.api.dns_port
+                    )
+                    .into(),
                 ])
             }
             Some(PortState::Error) => {
-                let marker = Span::styled("🗙", Style::default().fg(Color::Red));
+                let marker = Span::styled("🗙", self.theme.error);
                 Line::from(vec![
                     "- [".into(),
                     marker,
-                    format!("] error when probing DNS port (udp:{})", self.api.dns_port).into(),
+                    format!(
+                        "] error when probing DNS port (udp:{}{udp_note})",
+                        self.api.dns_port
+                    )
+                    .into(),
                 ])
             }
             None => {
-                let marker = Span::styled("?", Style::default().fg(Color::Yellow));
+                let marker = Span::styled("?", self.theme.warn);
                 Line::from(vec![
                     "- [".into(),
                     marker,
-                    format!("] DNS port (udp:{}) not yet probed", self.api.dns_port).into(),
+                    format!(
+                        "] DNS port (udp:{}{udp_note}) not yet probed",
+                        self.api.dns_port
+                    )
+                    .into(),
+                ])
+            }
+            Some(PortState::Skipped) => {
+                let marker = Span::styled("-", self.theme.muted);
+                Line::from(vec![
+                    "- [".into(),
+                    marker,
+                    Span::styled(
+                        format!("] DNS port (udp:{}) probe skipped", self.api.dns_port),
+                        self.theme.muted,
+                    ),
                 ])
             }
         };
 
         let status_line;
-        if self.dns_status.udp_port_state == Some(PortState::Open)
-            && self.dns_status.tcp_port_state == Some(PortState::Open)
+        if self.tasks.is_running(TaskKind::DNSStatus) {
+            status_line = Line::from(vec![
+                self.spinner(TaskKind::DNSStatus),
+                Span::styled(
+                    format!(" Probing...{}", self.elapsed_label(TaskKind::DNSStatus)),
+                    self.theme.text.bold(),
+                ),
+            ]);
+        } else if self.dns_status.udp_port_state == Some(PortState::Open)
+            && matches!(
+                self.dns_status.tcp_port_state,
+                Some(PortState::Open | PortState::Skipped)
+            )
             && self.dns_status.query_response_state == Some(ApiQueryResponseState::Healthy)
         {
-            status_line = Line::styled("Healthy", Style::default().fg(Color::Green).bold());
+            status_line = Line::styled("Healthy", self.theme.ok.bold());
         } else if self.dns_status.udp_port_state == Some(PortState::Closed)
             && self.dns_status.tcp_port_state == Some(PortState::Closed)
             && self.dns_status.query_response_state == Some(ApiQueryResponseState::NoResponse)
         {
-            status_line = Line::styled("No Response", Style::default().fg(Color::Red).bold());
+            status_line = Line::styled("No Response", self.theme.error.bold());
         } else if self.dns_status.udp_port_state.is_none()
             && self.dns_status.tcp_port_state.is_none()
             && self.dns_status.query_response_state.is_none()
         {
-            status_line = Line::styled(
-                "Not yet requested",
-                Style::default().fg(Color::White).bold(),
-            );
+            status_line = Line::styled("Not yet requested", self.theme.text.bold());
         } else {
-            status_line = Line::styled("Unhealthy", Style::default().fg(Color::Magenta).bold());
+            status_line = Line::styled("Unhealthy", self.theme.warn.bold());
         };
 
         let block = self.get_block(
@@ -183,11 +534,11 @@ impl App {
         let split_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Percentage(10),
-                Constraint::Percentage(30),
-                Constraint::Percentage(60),
+                Constraint::Max(1),
+                Constraint::Length(2),
+                Constraint::Min(3),
             ])
-            .split(block.inner(r));
+            .split(above_button(block.inner(r)));
         frame.render_widget(block, r);
 
         let status_par = Paragraph::new(status_line)
@@ -195,43 +546,95 @@ impl App {
             .wrap(Wrap { trim: true });
         frame.render_widget(status_par, split_layout[1]);
 
-        let area = self.centered_rect(70, 99, split_layout[2]);
-        let details_par = Paragraph::new(vec![tcp_port_line, udp_port_line, api_status_line])
+        // narrow tiles need the whole width for the details to not wrap every line
+        let details_width = if r.width < 60 { 100 } else { 70 };
+        let area = self.centered_rect(details_width, 99, split_layout[2]);
+        let mut details = vec![tcp_port_line];
+        details.extend(self.address_lines(&self.dns_status.tcp_addresses));
+        details.push(udp_port_line);
+        details.extend(self.address_lines(&self.dns_status.udp_addresses));
+        details.push(api_status_line);
+        let details_par = Paragraph::new(details)
             .left_aligned()
             .wrap(Wrap { trim: true })
-            .style(Style::default().fg(Color::White));
+            .style(self.theme.text);
         frame.render_widget(details_par, area);
     }
 
+    /// One line per probed address, only if the server has more than one address
+    fn address_lines(&self, probes: &[AddressProbe]) -> Vec<Line<'static>> {
+        if probes.len() < 2 {
+            return Vec::new();
+        }
+        probes
+            .iter()
+            .map(|probe| {
+                let marker = match probe.state {
+                    PortState::Open => Span::styled("✓", self.theme.ok),
+                    PortState::Skipped => Span::styled("-", self.theme.muted),
+                    _ => Span::styled("🗙", self.theme.error),
+                };
+                Line::from(vec![
+                    "↳ [".into(),
+                    marker,
+                    format!("] {}", probe.addr).into(),
+                ])
+            })
+            .collect()
+    }
+
+    /// Spinner of the operation, its first frame if the operation is not in flight
+    fn spinner(&self, kind: TaskKind) -> Span<'static> {
+        let elapsed = self.tasks.elapsed(kind).unwrap_or_default();
+        Span::styled(spinner_frame(elapsed), self.theme.warn.bold())
+    }
+
+    /// Time the operation is in flight like ` 1.2s`, empty if it is not in flight
+    fn elapsed_label(&self, kind: TaskKind) -> String {
+        self.tasks
+            .elapsed(kind)
+            .map(|elapsed| format!(" {:.1}s", elapsed.as_secs_f64()))
+            .unwrap_or_default()
+    }
+
     fn render_blocking_status_tile(&self, r: Rect, frame: &mut Frame) {
-        // TODO: render seconds and group names if blocking is disabled
         let blocking_line = {
             match &self.blocking_status {
                 Some(status) => {
                     if status.is_blocking_enabled {
                         vec![
-                            Line::from(Span::styled("Blocking", Style::default().fg(Color::Green))),
+                            Line::from(Span::styled("Blocking", self.theme.ok)),
                             Line::from(Span::from("DNS server is currently blocking")),
                         ]
                     } else {
-                        vec![
-                            Line::from(Span::styled(
-                                "Not Blocking",
-                                Style::default().fg(Color::Green),
-                            )),
+                        let mut lines = vec![
+                            Line::from(Span::styled("Not Blocking", self.theme.warn)),
                             Line::from(Span::from("DNS server is not blocking")),
-                        ]
+                        ];
+                        if let Some(secs) = status.unblocking_timer {
+                            lines.push(Line::from(format!("re-enabled in {secs}s")));
+                        }
+                        if let Some(groups) = &status.disabled_groups {
+                            lines.push(Line::from(format!("disabled groups: {groups}")));
+                        }
+                        lines
                     }
                 }
                 None => vec![
-                    Line::styled("Not queried", Style::default().fg(Color::White).bold()),
-                    Line::styled(
-                        "Blocking status is not set",
-                        Style::default().fg(Color::White).italic(),
-                    ),
+                    Line::styled("Not queried", self.theme.text.bold()),
+                    Line::styled("Blocking status is not set", self.theme.text.italic()),
                 ],
             }
         };
+        let mut blocking_line = blocking_line;
+        for kind in [TaskKind::SetBlocking, TaskKind::BlockingStatus] {
+            if self.tasks.is_running(kind) {
+                blocking_line.push(Line::from(vec![
+                    self.spinner(kind),
+                    format!(" {}...{}", kind.label(), self.elapsed_label(kind)).into(),
+                ]));
+            }
+        }
 
         let blocking_par = Paragraph::new(blocking_line)
             .centered()
@@ -246,7 +649,7 @@ impl App {
     fn render_refresh_list_tile(&self, r: Rect, frame: &mut Frame) {
         let status_line = match self.blocking_list_refresh_state {
             None => {
-                let marker = Span::styled("?", Style::default().fg(Color::Yellow).bold());
+                let marker = Span::styled("?", self.theme.warn.bold());
                 Line::from(vec![
                     "[".into(),
                     marker,
@@ -254,16 +657,17 @@ impl App {
                 ])
             }
             Some(status) => match status {
-                ActionState::Waiting => {
-                    let marker = Span::styled("?", Style::default().fg(Color::Yellow).bold());
-                    Line::from(vec![
-                        "[".into(),
-                        marker,
-                        "] Requested list update...".into(),
-                    ])
-                }
+                ActionState::Waiting => Line::from(vec![
+                    "[".into(),
+                    self.spinner(TaskKind::RefreshLists),
+                    format!(
+                        "] Requested list update...{}",
+                        self.elapsed_label(TaskKind::RefreshLists)
+                    )
+                    .into(),
+                ]),
                 ActionState::Success => {
-                    let marker = Span::styled("✓", Style::default().fg(Color::Green).bold());
+                    let marker = Span::styled("✓", self.theme.ok.bold());
                     Line::from(vec![
                         "[".into(),
                         marker,
@@ -271,7 +675,7 @@ impl App {
                     ])
                 }
                 ActionState::Failure => {
-                    let marker = Span::styled("🗙", Style::default().fg(Color::Red).bold());
+                    let marker = Span::styled("🗙", self.theme.error.bold());
                     Line::from(vec![
                         "[".into(),
                         marker,
@@ -291,10 +695,10 @@ impl App {
         let split_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Percentage(10), Constraint::Percentage(90)])
-            .split(block.inner(r));
+            .split(above_button(block.inner(r)));
         frame.render_widget(block, r);
 
-        let area = self.centered_rect(90, 50, split_layout[1]);
+        let area = self.status_area(split_layout[1]);
         let status_par = Paragraph::new(status_line)
             .centered()
             .wrap(Wrap { trim: true });
@@ -302,18 +706,133 @@ impl App {
     }
 
     fn render_query_tile(&self, r: Rect, frame: &mut Frame) {
-        let query_par = Paragraph::new("Query DNS").centered().block(self.get_block(
+        let input_line = if self.is_editing_query() {
+            Line::from(vec![
+                Span::styled("> ", self.theme.focus.bold()),
+                Span::from(self.query_input.clone()),
+                Span::styled("_", self.theme.focus),
+            ])
+        } else if self.query_input.is_empty() {
+            Line::styled(
+                "press Enter to query a domain, e.g. 'example.com AAAA'",
+                self.theme.text.italic(),
+            )
+        } else {
+            Line::from(vec!["> ".into(), Span::from(self.query_input.clone())])
+        };
+
+        let result_lines = match &self.dns_query_state {
+            None => vec![Line::styled("No query sent yet", self.theme.text.italic())],
+            Some(DNSQueryState::Waiting(query)) => vec![Line::from(vec![
+                "[".into(),
+                self.spinner(TaskKind::DNSQuery),
+                format!(
+                    "] Querying {} ({})...{}",
+                    query.query,
+                    query.query_type,
+                    self.elapsed_label(TaskKind::DNSQuery)
+                )
+                .into(),
+            ])],
+            Some(DNSQueryState::Success(query, resp)) => {
+                let style = match resp.response_type.as_str() {
+                    "BLOCKED" => self.theme.error,
+                    "RESOLVED" | "CACHED" | "CONDITIONAL" | "CUSTOMDNS" | "HOSTSFILE" => {
+                        self.theme.ok
+                    }
+                    _ => self.theme.warn,
+                };
+                vec![
+                    Line::from(vec![
+                        format!("{} ({}): ", query.query, query.query_type).into(),
+                        Span::styled(resp.response_type.clone(), style.bold()),
+                    ]),
+                    Line::from(format!("Return code: {}", resp.return_code)),
+                    Line::from(format!("Reason: {}", resp.reason)),
+                    Line::from(format!("Response: {}", resp.response)),
+                ]
+            }
+            Some(DNSQueryState::Failure(query, err)) => {
+                let marker = Span::styled("🗙", self.theme.error.bold());
+                vec![
+                    Line::from(vec![
+                        "[".into(),
+                        marker,
+                        format!("] Query for {} failed", query.query).into(),
+                    ]),
+                    Line::from(err.clone()),
+                ]
+            }
+        };
+
+        let block = self.get_block(
             CurrentFocus::QueryDNS,
             format!("[{}] Query DNS", CurrentFocus::QueryDNS as u8),
-        ));
+        );
+        let split_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(block.inner(r));
+        frame.render_widget(block, r);
 
-        frame.render_widget(query_par, r);
+        frame.render_widget(Paragraph::new(input_line), split_layout[1]);
+        let result_par = Paragraph::new(result_lines)
+            .wrap(Wrap { trim: true })
+            .style(self.theme.text);
+        frame.render_widget(result_par, split_layout[3]);
+    }
+
+    /// Renders the command palette at the top of the screen with the best matches below the input
+    fn render_palette(&self, palette: &CommandPalette, r: Rect, frame: &mut Frame) {
+        let matches = palette.matches();
+        let area = palette_area(r, matches.len().min(MAX_PALETTE_MATCHES));
+
+        let mut lines = vec![Line::from(vec![
+            Span::styled(": ", self.theme.focus.bold()),
+            Span::from(palette.input.clone()),
+            Span::styled("_", self.theme.focus),
+        ])];
+        if matches.is_empty() {
+            lines.push(Line::styled(
+                "no matching command",
+                self.theme.muted.italic(),
+            ));
+        }
+        for (i, palette_match) in matches.iter().take(MAX_PALETTE_MATCHES).enumerate() {
+            let entry = palette_match.entry;
+            let mut name = entry.name.to_string();
+            if let Some(argument) = entry.argument {
+                name = format!("{name} {argument}");
+            }
+            let style = if i == palette.selected {
+                self.theme.selected
+            } else {
+                self.theme.text
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!(" {name} "), style.bold()),
+                Span::styled(format!(" {}", entry.description), self.theme.muted),
+            ]));
+        }
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .style(self.theme.focus)
+            .title(Span::styled("Command Palette", Style::default().bold()));
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
     }
 
     fn render_cache_delete_tile(&self, r: Rect, frame: &mut Frame) {
         let status_line = match self.cache_delete_state {
             None => {
-                let marker = Span::styled("?", Style::default().fg(Color::Yellow).bold());
+                let marker = Span::styled("?", self.theme.warn.bold());
                 Line::from(vec![
                     "[".into(),
                     marker,
@@ -321,16 +840,17 @@ impl App {
                 ])
             }
             Some(status) => match status {
-                ActionState::Waiting => {
-                    let marker = Span::styled("?", Style::default().fg(Color::Yellow).bold());
-                    Line::from(vec![
-                        "[".into(),
-                        marker,
-                        "] Requested DNS cache deletion...".into(),
-                    ])
-                }
+                ActionState::Waiting => Line::from(vec![
+                    "[".into(),
+                    self.spinner(TaskKind::ClearCache),
+                    format!(
+                        "] Requested DNS cache deletion...{}",
+                        self.elapsed_label(TaskKind::ClearCache)
+                    )
+                    .into(),
+                ]),
                 ActionState::Success => {
-                    let marker = Span::styled("✓", Style::default().fg(Color::Green).bold());
+                    let marker = Span::styled("✓", self.theme.ok.bold());
                     Line::from(vec![
                         "[".into(),
                         marker,
@@ -338,7 +858,7 @@ impl App {
                     ])
                 }
                 ActionState::Failure => {
-                    let marker = Span::styled("🗙", Style::default().fg(Color::Red).bold());
+                    let marker = Span::styled("🗙", self.theme.error.bold());
                     Line::from(vec![
                         "[".into(),
                         marker,
@@ -355,29 +875,472 @@ impl App {
         let split_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Percentage(10), Constraint::Percentage(90)])
-            .split(block.inner(r));
+            .split(above_button(block.inner(r)));
         frame.render_widget(block, r);
 
-        let area = self.centered_rect(90, 50, split_layout[1]);
+        let area = self.status_area(split_layout[1]);
         let status_par = Paragraph::new(status_line)
             .centered()
             .wrap(Wrap { trim: true });
         frame.render_widget(status_par, area);
     }
 
-    fn render_title(&self, r: Rect, frame: &mut Frame) {
+    fn render_title(&self, layout: &MainLayout, frame: &mut Frame) {
+        const TITLE: &str = "Blocky TUI";
+        let title =
+            Paragraph::new(Text::styled(TITLE, self.theme.focus)).alignment(Alignment::Center);
+        // the compact layout has no space for a border around the title
+        let area = if layout.mode == LayoutMode::Compact {
+            frame.render_widget(title, layout.title);
+            layout.title
+        } else {
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .style(Style::default());
+            let inner = block.inner(layout.title);
+            frame.render_widget(title.block(block), layout.title);
+            inner
+        };
+
+        if !self.watchlist.entries.is_empty() {
+            // left of the centered title
+            let width = (area.width as usize).saturating_sub(TITLE.len()) / 2;
+            frame.render_widget(Paragraph::new(self.watchlist_lights(width)), area);
+        }
+
+        if let Some(elapsed) = self.tasks.longest_elapsed() {
+            let pending = self.tasks.running().count();
+            let activity = Line::from(vec![
+                Span::styled(spinner_frame(elapsed), self.theme.warn.bold()),
+                Span::styled(format!(" {pending} pending "), self.theme.muted),
+            ]);
+            frame.render_widget(Paragraph::new(activity).right_aligned(), area);
+        }
+    }
+
+    /// Traffic lights of the watchlist, failing entries come first so they are never cut off
+    fn watchlist_lights(&self, width: usize) -> Line<'static> {
+        let mut entries: Vec<&Watch> = self.watchlist.entries.iter().collect();
+        entries.sort_by_key(|watch| watch.severity());
+
+        let mut spans = vec![];
+        let mut used = 0;
+        for (index, watch) in entries.iter().enumerate() {
+            // the symbols differ as well, for the monochrome theme
+            let (symbol, style) = match &watch.verdict {
+                None => ("○", self.theme.muted),
+                Some(Verdict::Pass) => ("●", self.theme.ok),
+                Some(Verdict::Fail(_)) => ("✖", self.theme.error.bold()),
+                Some(Verdict::Error(_)) => ("▲", self.theme.warn),
+            };
+            let light = format!("{symbol} {} ", watch.expectation.domain);
+            // room for the count of the hidden entries, in case the next ones do not fit
+            let marker = format!("+{}", entries.len() - index);
+            if used + light.chars().count() + marker.len() > width {
+                if used + marker.len() <= width {
+                    spans.push(Span::styled(marker, self.theme.muted));
+                }
+                break;
+            }
+            used += light.chars().count();
+            spans.push(Span::styled(light, style));
+        }
+        Line::from(spans)
+    }
+
+    fn render_too_small(&self, r: Rect, frame: &mut Frame) {
+        let lines = vec![
+            Line::styled("Terminal too small", self.theme.error.bold()),
+            Line::from(format!("{}x{}", r.width, r.height)),
+            Line::from(format!("needs at least {MIN_WIDTH}x{MIN_HEIGHT}")),
+        ];
+        let height = (lines.len() as u16).min(r.height);
+        let area = Rect::new(r.x, r.y + (r.height - height) / 2, r.width, height);
+        frame.render_widget(
+            Paragraph::new(lines).centered().wrap(Wrap { trim: true }),
+            area,
+        );
+    }
+
+    fn render_event_log(&self, r: Rect, frame: &mut Frame) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(Span::styled(
+                "Event Log (? for help)",
+                Style::default().bold(),
+            ));
+        let visible_rows = block.inner(r).height as usize;
+
+        let history = self.notifications.history();
+        if history.is_empty() {
+            let par = Paragraph::new(Line::styled("No events yet", self.theme.text.italic()))
+                .centered()
+                .block(block);
+            frame.render_widget(par, r);
+            return;
+        }
+
+        // newest entries are at the bottom, scrolling moves the window up into the history
+        let end = history.len() - self.notifications.scroll.min(history.len() - 1);
+        let start = end.saturating_sub(visible_rows);
+        let lines: Vec<Line> = history[start..end]
+            .iter()
+            .map(|n| {
+                Line::from(vec![
+                    Span::styled(n.time_label(), self.theme.muted),
+                    " ".into(),
+                    self.notification_level_span(n.level),
+                    " ".into(),
+                    n.message.clone().into(),
+                ])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines).block(block), r);
+    }
+
+    fn render_log_view(&self, r: Rect, frame: &mut Frame) {
+        let follow = if self.log_view.follow {
+            Span::styled("following", self.theme.ok)
+        } else {
+            Span::styled("frozen", self.theme.warn)
+        };
+        let search = if self.log_view.is_searching {
+            Span::styled(format!("/{}_", self.log_view.search), self.theme.focus)
+        } else if self.log_view.search.is_empty() {
+            Span::styled("none", self.theme.muted)
+        } else {
+            Span::from(format!("/{}", self.log_view.search))
+        };
+        let header = Line::from(vec![
+            "level ≥ ".into(),
+            Span::styled(
+                self.log_view.min_level.as_str(),
+                self.log_level_style(self.log_view.min_level),
+            ),
+            " | ".into(),
+            follow,
+            " | search: ".into(),
+            search,
+        ]);
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(Span::styled("Logs (? for help)", Style::default().bold()));
+        let inner = block.inner(r);
+        frame.render_widget(block, r);
+        let split_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(inner);
+        frame.render_widget(Paragraph::new(header), split_layout[0]);
+
+        let records = self.log_view.visible_records(&LOG_BUFFER);
+        let visible_rows = split_layout[1].height as usize;
+        let end = records.len() - self.log_view.scroll.min(records.len().saturating_sub(1));
+        let start = end.saturating_sub(visible_rows);
+        let lines: Vec<Line> = records[start..end]
+            .iter()
+            .map(|r| {
+                Line::from(vec![
+                    Span::styled(utc_time_label(r.timestamp), self.theme.muted),
+                    " ".into(),
+                    Span::styled(
+                        format!("{:<5}", r.level.as_str()),
+                        self.log_level_style(r.level).bold(),
+                    ),
+                    " ".into(),
+                    r.message.clone().into(),
+                ])
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(lines), split_layout[1]);
+    }
+
+    fn render_bulk_test(&self, r: Rect, frame: &mut Frame) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .title(Span::styled(
+                "Bulk Test (? for help)",
+                Style::default().bold(),
+            ));
+        let Some(test) = &self.bulk_test else {
+            let par = Paragraph::new(Line::styled(
+                "No bulk test yet, run 'bulk test <file>' from the command palette",
+                self.theme.text.italic(),
+            ))
+            .centered()
+            .block(block);
+            frame.render_widget(par, r);
+            return;
+        };
+        let inner = block.inner(r);
+        frame.render_widget(block, r);
+        let split_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(inner);
+
+        let mut progress = vec![];
+        if self.tasks.is_running(TaskKind::BulkTest) {
+            progress.push(self.spinner(TaskKind::BulkTest));
+            progress.push(" ".into());
+        }
+        let order = if test.descending { "↓" } else { "↑" };
+        progress.extend([
+            Span::from(format!("{}/{} domains", test.results.len(), test.total)),
+            Span::styled(
+                format!(
+                    " | {} | type {} | sorted by {} {order}",
+                    test.source.display(),
+                    test.query_type,
+                    test.sort.label()
+                ),
+                self.theme.muted,
+            ),
+        ]);
+        frame.render_widget(Paragraph::new(Line::from(progress)), split_layout[0]);
+
+        let summary: Vec<Span> = summarize(&test.results)
+            .into_iter()
+            .flat_map(|(response_type, count)| {
+                let style = self.response_type_style(&response_type);
+                [
+                    Span::styled(format!("{count} {response_type}"), style.bold()),
+                    "  ".into(),
+                ]
+            })
+            .collect();
+        frame.render_widget(Paragraph::new(Line::from(summary)), split_layout[1]);
+
+        // the header takes the first row of the table
+        let visible_rows = split_layout[2].height.saturating_sub(1) as usize;
+        let rows: Vec<Row> = test
+            .results
+            .iter()
+            .skip(test.scroll)
+            .take(visible_rows)
+            .map(|result| {
+                Row::new([
+                    Span::from(result.domain.clone()),
+                    Span::styled(
+                        result.response_type().to_string(),
+                        self.response_type_style(result.response_type()),
+                    ),
+                    Span::from(result.response().to_string()),
+                    Span::from(result.reason().to_string()),
+                ])
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Percentage(30),
+                Constraint::Length(10),
+                Constraint::Percentage(30),
+                Constraint::Min(10),
+            ],
+        )
+        .header(Row::new(["Domain", "Type", "Response", "Reason"]).style(self.theme.muted.bold()));
+        frame.render_widget(table, split_layout[2]);
+    }
+
+    fn render_suite(&self, r: Rect, frame: &mut Frame) {
         let block = Block::default()
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
-            .style(Style::default());
+            .title(Span::styled(
+                "Test Suite (? for help)",
+                Style::default().bold(),
+            ));
+        let Some(run) = &self.suite_run else {
+            let par = Paragraph::new(Line::styled(
+                "No test suite yet, run 'run suite <file>' from the command palette",
+                self.theme.text.italic(),
+            ))
+            .centered()
+            .block(block);
+            frame.render_widget(par, r);
+            return;
+        };
+        let inner = block.inner(r);
+        frame.render_widget(block, r);
+        let split_layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(inner);
+
+        let mut progress = vec![];
+        if self.tasks.is_running(TaskKind::Suite) {
+            progress.push(self.spinner(TaskKind::Suite));
+            progress.push(" ".into());
+        }
+        progress.extend([
+            Span::from(format!("{}/{} checks", run.results.len(), run.total)),
+            Span::styled(format!(" | {}", run.source.display()), self.theme.muted),
+        ]);
+        frame.render_widget(Paragraph::new(Line::from(progress)), split_layout[0]);
+
+        let (passed, failed, errors) = suite::count(&run.results);
+        let summary = Line::from(vec![
+            Span::styled(format!("{passed} passed"), self.theme.ok.bold()),
+            "  ".into(),
+            Span::styled(format!("{failed} failed"), self.theme.error.bold()),
+            "  ".into(),
+            Span::styled(format!("{errors} errors"), self.theme.warn.bold()),
+        ]);
+        frame.render_widget(Paragraph::new(summary), split_layout[1]);
+
+        let visible_rows = split_layout[2].height.saturating_sub(1) as usize;
+        let rows: Vec<Row> = run
+            .results
+            .iter()
+            .skip(run.scroll)
+            .take(visible_rows)
+            .map(|result| {
+                let (status, style, details) = match &result.verdict {
+                    Verdict::Pass => ("PASS", self.theme.ok, ""),
+                    Verdict::Fail(message) => ("FAIL", self.theme.error, message.as_str()),
+                    Verdict::Error(message) => ("ERROR", self.theme.warn, message.as_str()),
+                };
+                Row::new([
+                    Span::styled(status, style.bold()),
+                    Span::from(result.server.clone()),
+                    Span::from(result.title()),
+                    Span::styled(details.to_string(), self.theme.muted),
+                ])
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(6),
+                Constraint::Percentage(20),
+                Constraint::Percentage(40),
+                Constraint::Min(10),
+            ],
+        )
+        .header(Row::new(["", "Server", "Check", "Details"]).style(self.theme.muted.bold()));
+        frame.render_widget(table, split_layout[2]);
+    }
+
+    /// Blocked domains and failed queries stand out, everything else was answered
+    fn response_type_style(&self, response_type: &str) -> Style {
+        match response_type {
+            "BLOCKED" | "ERROR" => self.theme.error,
+            _ => self.theme.ok,
+        }
+    }
+
+    fn log_level_style(&self, level: Level) -> Style {
+        match level {
+            Level::ERROR => self.theme.error,
+            Level::WARN => self.theme.warn,
+            Level::INFO => self.theme.ok,
+            Level::DEBUG => self.theme.text,
+            Level::TRACE => self.theme.muted,
+        }
+    }
+
+    /// Renders all key bindings of the current screen, generated from the keymap
+    fn render_help(&self, r: Rect, frame: &mut Frame) {
+        let context = self.screen_key_context();
+        let bindings = self.keymap.bindings_for(context);
+        let keys: Vec<String> = bindings
+            .iter()
+            .map(|(chords, _)| {
+                chords
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .collect();
+        let key_width = keys.iter().map(|k| k.chars().count()).max().unwrap_or(0);
+        let lines: Vec<Line> = keys
+            .iter()
+            .zip(bindings.iter())
+            .map(|(keys, (_, command))| {
+                Line::from(vec![
+                    Span::styled(format!("{keys:>key_width$}"), self.theme.focus.bold()),
+                    "  ".into(),
+                    command.description().into(),
+                ])
+            })
+            .collect();
+
+        let width = (key_width as u16 + 40).min(r.width);
+        let height = (lines.len() as u16 + 2).min(r.height);
+        let area = Rect::new(
+            r.x + (r.width - width) / 2,
+            r.y + (r.height - height) / 2,
+            width,
+            height,
+        );
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_type(BorderType::Thick)
+            .style(self.theme.text)
+            .title(Span::styled(
+                format!("Key Bindings: {}", context.label()),
+                Style::default().bold(),
+            ));
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    /// Renders the non-expired notifications as stacked toasts in the top right corner
+    fn render_toasts(&self, r: Rect, frame: &mut Frame) {
+        let width = (r.width / 3).max(30).min(r.width);
+        let mut y = r.y + 1;
+        for toast in self.notifications.active_toasts() {
+            let height = self.toast_height(toast, width);
+            if y + height > r.bottom() {
+                break;
+            }
+            let area = Rect::new(r.right().saturating_sub(width + 1), y, width, height);
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .border_type(BorderType::Rounded)
+                .style(self.notification_level_style(toast.level))
+                .title(self.notification_level_span(toast.level));
+            let par = Paragraph::new(toast.message.clone())
+                .wrap(Wrap { trim: true })
+                .style(self.theme.text)
+                .block(block);
+            frame.render_widget(Clear, area);
+            frame.render_widget(par, area);
+            y += height;
+        }
+    }
+
+    fn toast_height(&self, toast: &Notification, width: u16) -> u16 {
+        let inner_width = width.saturating_sub(2).max(1) as usize;
+        let text_rows = toast.message.chars().count().div_ceil(inner_width).max(1);
+        (text_rows as u16).min(4) + 2
+    }
 
-        let title = Paragraph::new(Text::styled(
-            "Blocky TUI",
-            Style::default().fg(Color::Yellow),
-        ))
-        .alignment(Alignment::Center)
-        .block(block);
-        frame.render_widget(title, r);
+    fn notification_level_style(&self, level: NotificationLevel) -> Style {
+        match level {
+            NotificationLevel::Info => self.theme.ok,
+            NotificationLevel::Warn => self.theme.warn,
+            NotificationLevel::Error => self.theme.error,
+        }
+    }
+
+    fn notification_level_span(&self, level: NotificationLevel) -> Span<'static> {
+        Span::styled(level.label(), self.notification_level_style(level).bold())
     }
 
     fn get_block(&self, tile: CurrentFocus, block_title: String) -> Block<'_> {
@@ -385,19 +1348,28 @@ impl App {
             let title = Span::styled(block_title, Style::default().bold());
             Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::Yellow))
+                .style(self.theme.focus)
                 .border_type(BorderType::Thick)
                 .title(title)
         } else {
             let title = Span::styled(block_title, Style::default());
             Block::default()
                 .borders(Borders::ALL)
-                .style(Style::default().fg(Color::White))
+                .style(self.theme.text)
                 .border_type(BorderType::Rounded)
                 .title(title)
         }
     }
 
+    /// Area of the status line of the action tiles, small tiles use their full height
+    fn status_area(&self, r: Rect) -> Rect {
+        if r.height < 4 {
+            self.centered_rect(90, 100, r)
+        } else {
+            self.centered_rect(90, 50, r)
+        }
+    }
+
     /// helper function to create a centered rect using up certain percentage of the available rect `r`
     fn centered_rect(&self, percent_x: u16, percent_y: u16, r: Rect) -> Rect {
         // Cut the given rectangle into three vertical pieces
@@ -421,3 +1393,125 @@ impl App {
             .split(popup_layout[1])[1] // Return the middle chunk
     }
 }
+
+#[cfg(test)]
+mod snapshot_tests;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tile_hit_testing() {
+        let layout = MainLayout::new(Rect::new(0, 0, 120, 40));
+        for tile in [
+            CurrentFocus::DNSStatus,
+            CurrentFocus::BlockingStatus,
+            CurrentFocus::RefreshLists,
+            CurrentFocus::DeleteCache,
+            CurrentFocus::QueryDNS,
+        ] {
+            let area = layout.tile_area(&tile);
+            assert_eq!(layout.tile_at(area.x, area.y), Some(tile.clone()));
+            assert_eq!(
+                layout.tile_at(area.right() - 1, area.bottom() - 1),
+                Some(tile)
+            );
+        }
+        assert_eq!(layout.tile_at(layout.title.x, layout.title.y), None);
+    }
+
+    #[test]
+    fn test_watchlist_lights_leave_the_title_intact() {
+        use crate::config::Config;
+        use crate::suite::{Expectation, Outcome};
+        use crate::watchlist::Watchlist;
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut app = App::new(Config::default()).unwrap();
+        let expectations = ["a.example", "b.example", "c.example"]
+            .map(|domain| Expectation::new(domain, Outcome::Resolved));
+        app.watchlist = Watchlist::new(None, expectations.to_vec());
+        let mut terminal = Terminal::new(TestBackend::new(MIN_WIDTH, MIN_HEIGHT)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+
+        // 25 columns are left of the title, the third light does not fit next to the count
+        let buffer = terminal.backend().buffer();
+        let title: String = (0..MIN_WIDTH).map(|x| buffer.get(x, 0).symbol()).collect();
+        assert!(title.starts_with("○ a.example +2"), "{title}");
+        assert!(title.contains("Blocky TUI"), "{title}");
+    }
+
+    #[test]
+    fn test_layout_mode_by_terminal_size() {
+        let mode = |w, h| LayoutMode::for_area(Rect::new(0, 0, w, h));
+        assert_eq!(mode(59, 40), LayoutMode::TooSmall);
+        assert_eq!(mode(120, 15), LayoutMode::TooSmall);
+        assert_eq!(mode(80, 24), LayoutMode::Compact);
+        assert_eq!(mode(120, 29), LayoutMode::Compact);
+        assert_eq!(mode(120, 40), LayoutMode::Standard);
+        assert_eq!(mode(200, 50), LayoutMode::Wide);
+    }
+
+    #[test]
+    fn test_compact_layout_fits_all_tiles() {
+        let layout = MainLayout::new(Rect::new(0, 0, 80, 24));
+        assert_eq!(layout.title.height, 1);
+        for tile in [
+            layout.dns_status,
+            layout.blocking_status,
+            layout.refresh_lists,
+            layout.delete_cache,
+            layout.query,
+        ] {
+            assert!(
+                tile.width >= 40 && tile.height >= 4,
+                "tile too small: {tile:?}"
+            );
+        }
+        assert_eq!(layout.query.width, 80);
+    }
+
+    #[test]
+    fn test_compact_layout_at_minimum_size_is_not_clipped() {
+        let area = Rect::new(0, 0, MIN_WIDTH, MIN_HEIGHT);
+        let layout = MainLayout::new(area);
+        assert_eq!(layout.mode, LayoutMode::Compact);
+        assert_eq!(layout.title.height, 1);
+        assert_eq!(layout.dns_status.height, 10);
+        assert_eq!(layout.blocking_status.height, 10);
+        assert_eq!(layout.refresh_lists.height, 5);
+        assert_eq!(layout.delete_cache.height, 5);
+        assert_eq!(layout.query.height, 6);
+        assert_eq!(layout.query.bottom(), area.bottom());
+        assert_eq!(
+            LayoutMode::for_area(Rect::new(0, 0, MIN_WIDTH, MIN_HEIGHT - 1)),
+            LayoutMode::TooSmall
+        );
+    }
+
+    #[test]
+    fn test_too_small_layout_has_no_tiles() {
+        let layout = MainLayout::new(Rect::new(0, 0, 40, 10));
+        assert_eq!(layout.mode, LayoutMode::TooSmall);
+        assert_eq!(layout.tile_at(5, 5), None);
+    }
+
+    #[test]
+    fn test_spinner_advances_with_elapsed_time() {
+        assert_eq!(spinner_frame(Duration::ZERO), "⠋");
+        assert_eq!(spinner_frame(Duration::from_millis(150)), "⠙");
+        assert_eq!(spinner_frame(SPINNER_FRAME_DURATION * 10), "⠋");
+    }
+
+    #[test]
+    fn test_button_area_is_inside_tile_border() {
+        let tile = Rect::new(10, 5, 30, 10);
+        let button = button_area(tile, "[ Refresh ]");
+        assert_eq!(button, Rect::new(19, 13, 11, 1));
+        assert_eq!(
+            button_area(Rect::new(0, 0, 30, 3), "[ Refresh ]"),
+            Rect::default()
+        );
+    }
+}
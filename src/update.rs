@@ -1,11 +1,29 @@
+use std::{path::Path, pin::pin, time::Duration};
+
 use anyhow::Result;
-use tracing::{debug, error, warn};
+use crossterm::event::KeyCode;
+use futures::{stream, StreamExt};
+use ratatui::layout::Rect;
+use tracing::{debug, error, info, warn};
 
 use crate::{
     action::Action,
-    api::DNSQuery,
-    app::{ActionState, ApiQueryResponseState, App, CurrentFocus, RunningState},
+    api::{ApiClient, ApiError, DNSQuery},
+    app::{
+        ActionState, ApiQueryResponseState, App, BlockingState, CurrentFocus, CurrentScreen,
+        DNSQueryState, RunningState, TerminalRequest,
+    },
+    bulk::{self, BulkResult, BulkTest},
+    config::Config,
+    logging::LOG_BUFFER,
+    notification::NotificationLevel,
+    palette::CommandPalette,
+    paste::{sanitize_domain_paste, sanitize_paste},
     port_check::{self, PortState},
+    suite::{self, CaseResult, Expectation, Suite, SuiteRun, Target, Verdict},
+    tasks::{ActionSender, TaskKind},
+    transport::Transport,
+    watchlist::Flip,
 };
 
 impl App {
@@ -14,8 +32,38 @@ impl App {
             debug!("updating on new action: {action:?}");
         }
         match action {
-            Action::Quit => self.change_running_state(RunningState::Done),
+            Action::Init => self.action_tx.send(Action::UpdateBlockingStatus)?,
+            Action::UpdateBlockingStatus => {
+                self.update_blocking_status();
+            }
+            Action::Quit => {
+                self.tasks.cancel_all();
+                self.change_running_state(RunningState::Done);
+            }
+            Action::TaskFinished(kind, id) => {
+                self.tasks.finish(*kind, *id);
+                // the next round starts an interval after the last one finished
+                if *kind == TaskKind::Watchlist && !self.tasks.is_running(TaskKind::Watchlist) {
+                    let interval = Duration::from_secs(self.config.watch_interval.max(1));
+                    self.watchlist.schedule(interval);
+                }
+            }
+            Action::Suspend => self.terminal_request = Some(TerminalRequest::Suspend),
+            Action::EditFile(path, then) => {
+                self.terminal_request = Some(TerminalRequest::Edit(path.clone(), then.clone()))
+            }
+            // the old config and key bindings are kept if the new ones are invalid
+            Action::ReloadConfig => match Config::load().and_then(|c| self.reload_config(c)) {
+                Ok(()) => self.notify(NotificationLevel::Info, "Config reloaded".to_string()),
+                Err(err) => self.notify(
+                    NotificationLevel::Error,
+                    format!("Could not reload config: {err:#}"),
+                ),
+            },
             Action::JumpToTile(tile_num) => {
+                if self.is_editing_query() {
+                    self.is_currently_editing = false;
+                }
                 self.set_tile_to_num(*tile_num);
                 self.action_tx.send(Action::Render)?;
             }
@@ -32,13 +80,23 @@ impl App {
             }
             Action::SetTCPPortState(port_state) => {
                 self.dns_status.tcp_port_state = Some(*port_state);
+                self.dns_status.tcp_addresses.clear();
             }
             Action::SetUDPPortState(port_state) => {
                 self.dns_status.udp_port_state = Some(*port_state);
+                self.dns_status.udp_addresses.clear();
+            }
+            Action::SetTCPAddressStates(probes) => {
+                self.dns_status.tcp_port_state = Some(PortState::summarize(probes));
+                self.dns_status.tcp_addresses = probes.clone();
+            }
+            Action::SetUDPAddressStates(probes) => {
+                self.dns_status.udp_port_state = Some(PortState::summarize(probes));
+                self.dns_status.udp_addresses = probes.clone();
             }
             Action::UpdateTile => {
                 if let CurrentFocus::DNSStatus = self.current_focus {
-                    self.update_dns_tile();
+                    self.action_tx.send(Action::UpdateDNSStatus)?;
                 }
             }
             Action::RefreshLists => {
@@ -46,6 +104,10 @@ impl App {
             }
             Action::SetRefreshListState(action_state) => {
                 self.blocking_list_refresh_state = Some(*action_state);
+                // new lists are the most likely reason for a watched domain to flip
+                if *action_state == ActionState::Success {
+                    self.watchlist.check_now();
+                }
             }
             Action::ClearDNSCache => {
                 self.clear_dns_cache();
@@ -53,125 +115,709 @@ impl App {
             Action::SetDNSCacheClearState(action_state) => {
                 self.cache_delete_state = Some(*action_state);
             }
+            Action::SetBlockingState(blocking_state) => {
+                self.blocking_status = Some(blocking_state.clone());
+            }
+            Action::EnableDNSBlocking => {
+                self.set_blocking(true, None);
+            }
+            Action::DisableDNSBlocking(duration) => {
+                self.set_blocking(false, *duration);
+            }
+            Action::ShowDialog(dialog) => {
+                self.show_dialog(dialog.clone());
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::DialogAccept => {
+                if let Some(action) = self.close_dialog() {
+                    self.action_tx.send(action)?;
+                }
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::DialogCancel => {
+                self.close_dialog();
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::DialogToggleSelection => {
+                if let Some(dialog) = self.dialog.as_mut() {
+                    dialog.toggle_selection();
+                }
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::Notify(level, message) => {
+                self.notify(*level, message.clone());
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::ToggleEventLog => {
+                self.toggle_event_log();
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::ToggleLogView => {
+                self.toggle_log_view();
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::CycleLogLevel => {
+                self.log_view.cycle_level();
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::ToggleLogFollow => {
+                self.log_view.set_follow(!self.log_view.follow, &LOG_BUFFER);
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::StartLogSearch => {
+                self.log_view.is_searching = true;
+                self.is_currently_editing = true;
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::ScrollUp if self.palette.is_some() => {
+                if let Some(palette) = self.palette.as_mut() {
+                    palette.select_previous();
+                }
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::ScrollDown if self.palette.is_some() => {
+                if let Some(palette) = self.palette.as_mut() {
+                    palette.select_next();
+                }
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::SelectPaletteEntry(index) => {
+                if let Some(palette) = self.palette.as_mut() {
+                    palette.selected = *index;
+                }
+            }
+            Action::Resize(width, height) => {
+                self.terminal_area = Rect::new(0, 0, *width, *height);
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::ScrollUp => {
+                match self.current_screen {
+                    CurrentScreen::EventLog => self.notifications.scroll_up(1),
+                    CurrentScreen::Logs => self.log_view.scroll_up(1, &LOG_BUFFER),
+                    CurrentScreen::BulkTest => {
+                        if let Some(bulk_test) = self.bulk_test.as_mut() {
+                            bulk_test.scroll_up(1);
+                        }
+                    }
+                    CurrentScreen::Suite => {
+                        if let Some(suite_run) = self.suite_run.as_mut() {
+                            suite_run.scroll_up(1);
+                        }
+                    }
+                    _ => {}
+                }
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::ScrollDown => {
+                match self.current_screen {
+                    CurrentScreen::EventLog => self.notifications.scroll_down(1),
+                    CurrentScreen::Logs => self.log_view.scroll_down(1, &LOG_BUFFER),
+                    CurrentScreen::BulkTest => {
+                        if let Some(bulk_test) = self.bulk_test.as_mut() {
+                            bulk_test.scroll_down(1);
+                        }
+                    }
+                    CurrentScreen::Suite => {
+                        if let Some(suite_run) = self.suite_run.as_mut() {
+                            suite_run.scroll_down(1);
+                        }
+                    }
+                    _ => {}
+                }
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::Key(key) if self.is_currently_editing => {
+                match key.code {
+                    KeyCode::Char(c) => self.insert_into_active_input(&c.to_string()),
+                    KeyCode::Backspace => self.delete_from_active_input(),
+                    _ => {}
+                }
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::Paste(text) => {
+                self.paste(text);
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::SubmitInput => {
+                self.submit_active_input()?;
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::CancelInput => {
+                self.cancel_active_input();
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::OpenPalette => {
+                self.palette = Some(CommandPalette::default());
+                self.is_currently_editing = true;
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::StartQueryInput => {
+                self.current_focus = CurrentFocus::QueryDNS;
+                self.is_currently_editing = true;
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::SubmitDNSQuery(query) => {
+                self.submit_dns_query(query.clone());
+            }
+            Action::SetDNSQueryState(query_state) => {
+                self.dns_query_state = Some(query_state.clone());
+            }
+            Action::UpdateDNSStatus => {
+                self.update_dns_tile();
+            }
+            Action::ToggleHelp => {
+                self.show_help = !self.show_help;
+                self.action_tx.send(Action::Render)?;
+            }
+            Action::StartBulkTest(path, query_type) => {
+                self.start_bulk_test(path, query_type);
+            }
+            Action::BulkTestLoaded(path, query_type, total) => {
+                info!("bulk testing {total} domains of {path:?}");
+                self.bulk_test = Some(BulkTest::new(path.clone(), query_type.clone(), *total));
+                self.current_screen = CurrentScreen::BulkTest;
+            }
+            Action::BulkResult(result) => self.add_bulk_result(result.clone()),
+            Action::ShowBulkTest => {
+                self.current_screen = CurrentScreen::BulkTest;
+            }
+            Action::ExportBulkResults(path) => self.export_bulk_results(path),
+            Action::CycleBulkSort => {
+                if let Some(bulk_test) = self.bulk_test.as_mut() {
+                    bulk_test.cycle_sort();
+                }
+            }
+            Action::ReverseBulkSort => {
+                if let Some(bulk_test) = self.bulk_test.as_mut() {
+                    bulk_test.reverse();
+                }
+            }
+            Action::StartSuite(path) => self.start_suite(path),
+            Action::SuiteLoaded(path, total) => {
+                self.suite_run = Some(SuiteRun::new(path.clone(), *total));
+                self.current_screen = CurrentScreen::Suite;
+            }
+            Action::SuiteResult(result) => self.add_suite_result(result.clone()),
+            Action::ShowSuite => {
+                self.current_screen = CurrentScreen::Suite;
+            }
+            Action::CheckWatchlist => self.check_watchlist(),
+            Action::WatchResult(expectation, verdict) => {
+                self.record_watch_result(expectation, verdict.clone())
+            }
+            Action::Watch(expectation) => {
+                self.watchlist.add(expectation.clone());
+                self.save_watchlist(format!("Watching {}", expectation.label()));
+                self.watchlist.check_now();
+            }
+            Action::Unwatch(domain) => {
+                if self.watchlist.remove(domain) {
+                    self.save_watchlist(format!("Stopped watching {domain}"));
+                } else {
+                    self.notify(
+                        NotificationLevel::Warn,
+                        format!("{domain} is not on the watchlist"),
+                    );
+                }
+            }
+            Action::CloseScreen => {
+                self.current_screen = CurrentScreen::Main;
+                self.action_tx.send(Action::Render)?;
+            }
             _ => {}
         }
         Ok(())
     }
 
-    fn clear_dns_cache(&self) {
-        let tx = self.action_tx.clone();
+    /// Text inputs in order of precedence: command palette, log search, query tile
+    fn insert_into_active_input(&mut self, text: &str) {
+        if let Some(palette) = self.palette.as_mut() {
+            palette.push_str(text);
+        } else if self.log_view.is_searching {
+            self.log_view.search.push_str(text);
+            self.log_view.scroll = 0;
+        } else if self.is_editing_query() {
+            self.query_input.push_str(text);
+        }
+    }
+
+    /// Pastes into the active text input, URLs are reduced to their hostname for domain inputs.
+    /// Without an active input on the main screen, the text is pasted into the query tile.
+    fn paste(&mut self, text: &str) {
+        if self.palette.is_some() || self.is_editing_query() {
+            self.insert_into_active_input(&sanitize_domain_paste(text));
+        } else if self.log_view.is_searching {
+            self.insert_into_active_input(&sanitize_paste(text));
+        } else if self.current_screen == CurrentScreen::Main
+            && self.dialog.is_none()
+            && !self.show_help
+        {
+            self.current_focus = CurrentFocus::QueryDNS;
+            self.is_currently_editing = true;
+            self.query_input = sanitize_domain_paste(text);
+        }
+    }
+
+    fn delete_from_active_input(&mut self) {
+        if let Some(palette) = self.palette.as_mut() {
+            palette.pop();
+        } else if self.log_view.is_searching {
+            self.log_view.search.pop();
+            self.log_view.scroll = 0;
+        } else if self.is_editing_query() {
+            self.query_input.pop();
+        }
+    }
+
+    fn submit_active_input(&mut self) -> Result<()> {
+        if let Some(palette) = self.palette.take() {
+            self.is_currently_editing = false;
+            if let Some(palette_match) = palette.selected_match() {
+                self.send_palette_command(palette_match)?;
+            }
+        } else if self.log_view.is_searching {
+            self.log_view.is_searching = false;
+            self.is_currently_editing = false;
+        } else if self.is_editing_query() {
+            self.is_currently_editing = false;
+            match DNSQuery::parse(&self.query_input) {
+                Some(query) => self.action_tx.send(Action::SubmitDNSQuery(query))?,
+                None => self.notify(NotificationLevel::Warn, "Enter a domain to query"),
+            }
+        }
+        Ok(())
+    }
+
+    fn cancel_active_input(&mut self) {
+        if self.palette.take().is_some() {
+            self.is_currently_editing = false;
+        } else if self.log_view.is_searching {
+            self.log_view.search.clear();
+            self.log_view.is_searching = false;
+            self.is_currently_editing = false;
+            self.log_view.scroll = 0;
+        } else if self.is_editing_query() {
+            self.query_input.clear();
+            self.is_currently_editing = false;
+        }
+    }
+
+    fn submit_dns_query(&mut self, query: DNSQuery) {
+        self.dns_query_state = Some(DNSQueryState::Waiting(query.clone()));
         let api_client = self.api.clone();
-        tokio::spawn(async move {
-            tx.send(Action::SetDNSCacheClearState(ActionState::Waiting))
-                .unwrap();
-            match api_client.post_clear_dns_cache().await {
-                Ok(resp) => {
-                    if resp.status() == 200 {
-                        debug!("successfully deleted DNS cache! {resp:?}");
-                        tx.send(Action::SetDNSCacheClearState(ActionState::Success))
-                            .unwrap()
-                    } else {
-                        warn!("deleting DNS cache did not work! {resp:?}");
-                        tx.send(Action::SetDNSCacheClearState(ActionState::Failure))
-                            .unwrap()
-                    }
-                }
+        // a new query replaces the one in flight
+        self.tasks.spawn(TaskKind::DNSQuery, |tx| async move {
+            match api_client.post_dnsquery(query.clone()).await {
+                Ok(resp) => tx.send(Action::SetDNSQueryState(DNSQueryState::Success(
+                    query, resp,
+                ))),
                 Err(err) => {
-                    warn!("could not issue a DNS cache deletion POST command! {err}");
-                    tx.send(Action::SetDNSCacheClearState(ActionState::Failure))
-                        .unwrap()
+                    warn!("DNS query for {} failed: {err}", query.query);
+                    tx.send(Action::Notify(
+                        NotificationLevel::Error,
+                        format!("DNS query for {} failed: {err}", query.query),
+                    ));
+                    tx.send(Action::SetDNSQueryState(DNSQueryState::Failure(
+                        query,
+                        err.to_string(),
+                    )))
                 }
             }
         });
     }
 
-    fn refresh_blocking_lists(&self) {
-        let tx = self.action_tx.clone();
+    fn start_bulk_test(&mut self, path: &Path, query_type: &str) {
+        // the results of the running test would be mixed up with the new ones
+        if self.tasks.is_running(TaskKind::BulkTest) {
+            self.notify(NotificationLevel::Warn, "A bulk test is already running");
+            return;
+        }
+        let path = path.to_path_buf();
         let api_client = self.api.clone();
-        tokio::spawn(async move {
-            tx.send(Action::SetRefreshListState(ActionState::Waiting))
-                .unwrap();
-            match api_client.post_refresh_list_cmd().await {
-                Ok(resp) => {
-                    if resp.status() == 200 {
-                        debug!("refreshing worked! {resp:?}");
-                        tx.send(Action::SetRefreshListState(ActionState::Success))
-                            .unwrap()
-                    } else if resp.status() == 500 {
-                        warn!("List refresh error {resp:?}");
-                        tx.send(Action::SetRefreshListState(ActionState::Failure))
-                            .unwrap()
-                    } else {
-                        warn!("received unknown response code from blocking list refresh command");
-                        tx.send(Action::SetRefreshListState(ActionState::Failure))
-                            .unwrap()
-                    }
+        let query_type = query_type.to_string();
+        let concurrency = self.config.bulk_concurrency;
+        // the list may be large or on a slow mount, so it is read by the task
+        self.tasks.spawn(TaskKind::BulkTest, |tx| async move {
+            let domains = match bulk::read_domains(&path) {
+                Ok(domains) => domains,
+                Err(err) => {
+                    warn!("could not start bulk test: {err:#}");
+                    tx.send(Action::Notify(NotificationLevel::Error, format!("{err:#}")));
+                    return;
                 }
+            };
+            tx.send(Action::BulkTestLoaded(
+                path,
+                query_type.clone(),
+                domains.len(),
+            ));
+            let mut results = pin!(bulk::query_all(
+                api_client,
+                domains,
+                query_type,
+                concurrency
+            ));
+            while let Some(result) = results.next().await {
+                tx.send(Action::BulkResult(result));
+            }
+        });
+    }
+
+    fn add_bulk_result(&mut self, result: BulkResult) {
+        let Some(bulk_test) = self.bulk_test.as_mut() else {
+            return;
+        };
+        bulk_test.push(result);
+        if bulk_test.is_complete() {
+            let summary = bulk::summary_line(&bulk_test.results);
+            info!("bulk test finished: {summary}");
+            self.notify(
+                NotificationLevel::Info,
+                format!("Bulk test finished, {summary}"),
+            );
+        }
+    }
+
+    fn start_suite(&mut self, path: &Path) {
+        if self.tasks.is_running(TaskKind::Suite) {
+            self.notify(NotificationLevel::Warn, "A test suite is already running");
+            return;
+        }
+        let path = path.to_path_buf();
+        let config = self.config.clone();
+        // without servers the suite checks the server the TUI is connected to
+        let connected = Target {
+            name: self.config.server_name(),
+            api: self.api.clone(),
+        };
+        // the suite file is read and the clients are built by the task, it may take a while
+        self.tasks.spawn(TaskKind::Suite, |tx| async move {
+            let loaded = Suite::load(&path)
+                .and_then(|suite| Ok((suite::profiles(&config, &suite.servers)?, suite)));
+            let (profiles, suite) = match loaded {
+                Ok(loaded) => loaded,
                 Err(err) => {
-                    warn!("could not issue a refresh blocking lists POST command! {err}");
-                    tx.send(Action::SetRefreshListState(ActionState::Failure))
-                        .unwrap()
+                    warn!("could not start test suite: {err:#}");
+                    tx.send(Action::Notify(NotificationLevel::Error, format!("{err:#}")));
+                    return;
                 }
+            };
+            let servers = profiles.len().max(1);
+            info!("running test suite {path:?} against {servers} servers");
+            tx.send(Action::SuiteLoaded(path, suite.checks() * servers));
+
+            let concurrency = config.bulk_concurrency;
+            let mut results = if profiles.is_empty() {
+                suite::run(vec![connected], suite.tests, concurrency).boxed()
+            } else {
+                suite::run_profiles(profiles, suite.tests, concurrency).boxed()
+            };
+            while let Some(result) = results.next().await {
+                tx.send(Action::SuiteResult(result));
             }
         });
     }
 
-    fn update_dns_tile(&mut self) {
-        let tx = self.action_tx.clone();
-        let query = DNSQuery {
-            query: "www.wikipedia.org",
-            query_type: "A",
+    fn add_suite_result(&mut self, result: CaseResult) {
+        let Some(suite_run) = self.suite_run.as_mut() else {
+            return;
         };
-        let dns_query = query.clone();
+        suite_run.results.push(result);
+        if suite_run.is_complete() {
+            let summary = suite::summary_line(&suite_run.results);
+            info!("test suite finished: {summary}");
+            let level = if suite_run.results.iter().all(CaseResult::passed) {
+                NotificationLevel::Info
+            } else {
+                NotificationLevel::Error
+            };
+            self.notify(level, format!("Test suite finished, {summary}"));
+        }
+    }
+
+    fn check_watchlist(&mut self) {
+        let expectations = self.watchlist.expectations();
+        if expectations.is_empty() {
+            return;
+        }
+        debug!("checking {} domains of the watchlist", expectations.len());
         let api_client = self.api.clone();
-        let api_port = self.api.api_port;
-        let dns_port = self.api.dns_port;
-        tokio::spawn(async move {
-            match api_client.post_dnsquery(dns_query).await {
-                Ok(it) => {
-                    if it.returnCode == "NOERROR" {
-                        tx.send(Action::SetDNSStatus(ApiQueryResponseState::Healthy))
-                            .unwrap()
-                    } else {
-                        tx.send(Action::SetDNSStatus(ApiQueryResponseState::Unhealthy))
-                            .unwrap()
+        let concurrency = self.config.bulk_concurrency;
+        let spawned = self.tasks.spawn(TaskKind::Watchlist, |tx| async move {
+            let mut verdicts = pin!(stream::iter(expectations)
+                .map(|expectation| {
+                    let api_client = api_client.clone();
+                    async move {
+                        let verdict = expectation.check_all(&api_client).await;
+                        (expectation, verdict)
                     }
+                })
+                .buffer_unordered(concurrency.max(1)));
+            while let Some((expectation, verdict)) = verdicts.next().await {
+                tx.send(Action::WatchResult(expectation, verdict));
+            }
+        });
+        if spawned {
+            self.watchlist.started();
+        }
+    }
+
+    fn record_watch_result(&mut self, expectation: &Expectation, verdict: Verdict) {
+        match self.watchlist.record(expectation, verdict) {
+            Some(Flip::Failed(message)) => {
+                warn!(
+                    "watched expectation failed: {}: {message}",
+                    expectation.label()
+                );
+                self.notify(
+                    NotificationLevel::Error,
+                    format!("Watchlist: {} failed, {message}", expectation.label()),
+                );
+            }
+            Some(Flip::Recovered) => {
+                info!("watched expectation holds again: {}", expectation.label());
+                self.notify(
+                    NotificationLevel::Info,
+                    format!("Watchlist: {} holds again", expectation.label()),
+                );
+            }
+            None => {}
+        }
+    }
+
+    /// Saves the changed watchlist and confirms the change with `message`
+    fn save_watchlist(&mut self, message: String) {
+        match self.watchlist.save() {
+            Ok(()) => self.notify(NotificationLevel::Info, message),
+            Err(err) => {
+                warn!("could not save the watchlist: {err:#}");
+                self.notify(
+                    NotificationLevel::Error,
+                    format!("Could not save the watchlist: {err:#}"),
+                )
+            }
+        }
+    }
+
+    fn export_bulk_results(&mut self, path: &Path) {
+        let results = match &self.bulk_test {
+            Some(bulk_test) if !bulk_test.results.is_empty() => &bulk_test.results,
+            _ => {
+                self.notify(NotificationLevel::Warn, "No bulk test results to export");
+                return;
+            }
+        };
+        match bulk::export_to_file(results, path) {
+            Ok(()) => {
+                let message = format!("Exported {} results to {}", results.len(), path.display());
+                self.notify(NotificationLevel::Info, message)
+            }
+            Err(err) => {
+                warn!("could not export bulk test results: {err:#}");
+                self.notify(
+                    NotificationLevel::Error,
+                    format!("Could not export results: {err:#}"),
+                )
+            }
+        }
+    }
+
+    fn clear_dns_cache(&mut self) {
+        let api_client = self.api.clone();
+        self.tasks.spawn(TaskKind::ClearCache, |tx| async move {
+            tx.send(Action::SetDNSCacheClearState(ActionState::Waiting));
+            match api_client.post_clear_dns_cache().await {
+                Ok(()) => {
+                    debug!("successfully deleted DNS cache!");
+                    tx.send(Action::SetDNSCacheClearState(ActionState::Success));
+                    tx.send(Action::Notify(
+                        NotificationLevel::Info,
+                        "DNS cache deleted".to_string(),
+                    ))
                 }
                 Err(err) => {
-                    error!(%err);
-                    tx.send(Action::SetDNSStatus(ApiQueryResponseState::NoResponse))
-                        .unwrap()
+                    warn!("deleting DNS cache did not work! {err}");
+                    tx.send(Action::SetDNSCacheClearState(ActionState::Failure));
+                    tx.send(Action::Notify(
+                        NotificationLevel::Error,
+                        format!("Deleting DNS cache failed: {err}"),
+                    ))
                 }
-            };
+            }
         });
+    }
 
-        let domain = self.api.url.clone();
-        let tx = self.action_tx.clone();
-        tokio::spawn(async move {
-            match port_check::check_tcp_port(domain.to_string(), api_port).await {
-                Ok(port_state) => {
-                    tx.send(Action::SetTCPPortState(port_state)).unwrap();
+    fn refresh_blocking_lists(&mut self) {
+        let api_client = self.api.clone();
+        self.tasks.spawn(TaskKind::RefreshLists, |tx| async move {
+            tx.send(Action::SetRefreshListState(ActionState::Waiting));
+            match api_client.post_refresh_list_cmd().await {
+                Ok(()) => {
+                    debug!("refreshing worked!");
+                    tx.send(Action::SetRefreshListState(ActionState::Success));
+                    tx.send(Action::Notify(
+                        NotificationLevel::Info,
+                        "Blocking lists refreshed".to_string(),
+                    ))
                 }
-                Err(r) => {
-                    error!("error testing TCP port: {:?}", r);
-                    tx.send(Action::SetTCPPortState(PortState::Error)).unwrap();
+                Err(err @ ApiError::Status { status: 500, .. }) => {
+                    warn!("List refresh error {err}");
+                    tx.send(Action::SetRefreshListState(ActionState::Failure));
+                    tx.send(Action::Notify(
+                        NotificationLevel::Error,
+                        format!("Blocky failed to refresh the blocking lists: {err}"),
+                    ))
+                }
+                Err(err @ ApiError::Status { .. }) => {
+                    warn!(
+                        "received unknown response code from blocking list refresh command: {err}"
+                    );
+                    tx.send(Action::SetRefreshListState(ActionState::Failure));
+                    tx.send(Action::Notify(
+                        NotificationLevel::Warn,
+                        format!("Unexpected response on list refresh: {err}"),
+                    ))
+                }
+                Err(err) => {
+                    warn!("could not issue a refresh blocking lists POST command! {err}");
+                    tx.send(Action::SetRefreshListState(ActionState::Failure));
+                    tx.send(Action::Notify(
+                        NotificationLevel::Error,
+                        format!("Could not refresh lists: {err}"),
+                    ))
+                }
+            }
+        });
+    }
+
+    fn update_blocking_status(&mut self) {
+        let api_client = self.api.clone();
+        self.tasks.spawn(TaskKind::BlockingStatus, |tx| async move {
+            match api_client.get_blocking_status().await {
+                Ok(status) => {
+                    let groups = status.disabled_groups.join(", ");
+                    tx.send(Action::SetBlockingState(BlockingState {
+                        is_blocking_enabled: status.enabled,
+                        unblocking_timer: (status.auto_enable_in_sec > 0)
+                            .then_some(status.auto_enable_in_sec),
+                        disabled_groups: (!groups.is_empty()).then_some(groups),
+                    }))
+                }
+                Err(err) => {
+                    warn!("could not query blocking status! {err}");
+                    tx.send(Action::Notify(
+                        NotificationLevel::Warn,
+                        format!("Could not query blocking status: {err}"),
+                    ))
                 }
             }
         });
+    }
 
-        let domain = self.api.url.clone();
-        let dns_query = query.clone();
-        let tx = self.action_tx.clone();
-        tokio::spawn(async move {
-            match port_check::check_dns(domain.to_string(), dns_port, dns_query).await {
-                Ok(port_state) => {
-                    tx.send(Action::SetUDPPortState(port_state)).unwrap();
+    /// Enables or disables blocking, the duration is only used when disabling
+    fn set_blocking(&mut self, enable: bool, duration: Option<Duration>) {
+        let api_client = self.api.clone();
+        let state = if enable { "enable" } else { "disable" };
+        self.tasks.spawn(TaskKind::SetBlocking, |tx| async move {
+            let result = if enable {
+                api_client.get_enable_blocking().await
+            } else {
+                api_client.get_disable_blocking(duration).await
+            };
+            match result {
+                Ok(()) => {
+                    debug!("{state}d blocking");
+                    tx.send(Action::Notify(
+                        NotificationLevel::Info,
+                        format!("Blocking {state}d"),
+                    ))
                 }
-                Err(r) => {
-                    error!("error querying UDP port: {:?}", r);
-                    tx.send(Action::SetUDPPortState(PortState::Error)).unwrap();
+                Err(err) => {
+                    warn!("could not {state} blocking! {err}");
+                    tx.send(Action::Notify(
+                        NotificationLevel::Error,
+                        format!("Could not {state} blocking: {err}"),
+                    ))
                 }
             }
+            // the tile should always show the state blocky reports
+            tx.send(Action::UpdateBlockingStatus);
         });
     }
+
+    fn update_dns_tile(&mut self) {
+        let query = DNSQuery {
+            query: "www.wikipedia.org".to_string(),
+            query_type: "A".to_string(),
+        };
+        let api_client = self.api.clone();
+        self.tasks.spawn(TaskKind::DNSStatus, |tx| async move {
+            // the API, the API port and the DNS port are probed concurrently
+            tokio::join!(
+                probe_api_query(&api_client, query.clone(), &tx),
+                probe_api_port(&api_client, &tx),
+                probe_dns_port(&api_client, query, &tx),
+            );
+        });
+    }
+}
+
+async fn probe_api_query(api_client: &ApiClient, query: DNSQuery, tx: &ActionSender) {
+    match api_client.post_dnsquery(query).await {
+        Ok(it) => {
+            if it.return_code == "NOERROR" {
+                tx.send(Action::SetDNSStatus(ApiQueryResponseState::Healthy))
+            } else {
+                tx.send(Action::SetDNSStatus(ApiQueryResponseState::Unhealthy))
+            }
+        }
+        Err(err) => {
+            error!(%err);
+            tx.send(Action::SetDNSStatus(ApiQueryResponseState::NoResponse));
+            tx.send(Action::Notify(
+                NotificationLevel::Error,
+                format!("DNS query via API failed: {err}"),
+            ))
+        }
+    };
+}
+
+async fn probe_api_port(api_client: &ApiClient, tx: &ActionSender) {
+    // the probe takes the same route as the API requests, a proxy can not be probed
+    let result = match &api_client.transport {
+        Transport::Direct => {
+            port_check::check_tcp_port(api_client.url.to_string(), api_client.api_port)
+                .await
+                .map(Action::SetTCPAddressStates)
+        }
+        Transport::UnixSocket(path) => port_check::check_unix_socket(path)
+            .await
+            .map(Action::SetTCPPortState),
+        Transport::Proxy(_) => Ok(Action::SetTCPPortState(PortState::Skipped)),
+    };
+    match result {
+        Ok(action) => tx.send(action),
+        Err(r) => {
+            error!("error testing TCP port: {:?}", r);
+            tx.send(Action::SetTCPPortState(PortState::Error));
+            tx.send(Action::Notify(
+                NotificationLevel::Error,
+                format!("Probing API port failed: {r}"),
+            ));
+        }
+    }
+}
+
+async fn probe_dns_port(api_client: &ApiClient, query: DNSQuery, tx: &ActionSender) {
+    match port_check::check_dns(api_client.url.to_string(), api_client.dns_port, query).await {
+        Ok(probes) => tx.send(Action::SetUDPAddressStates(probes)),
+        Err(r) => {
+            error!("error querying UDP port: {:?}", r);
+            tx.send(Action::SetUDPPortState(PortState::Error));
+            tx.send(Action::Notify(
+                NotificationLevel::Error,
+                format!("Probing DNS port failed: {r}"),
+            ));
+        }
+    }
 }
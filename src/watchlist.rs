@@ -0,0 +1,255 @@
+//! Critical domains which are re-checked against the connected server on a schedule, e.g. the
+//! banking site which must never be blocked after a list refresh
+//!
+//! The watchlist is saved in `watchlist.yaml` in the config directory, as a list of the
+//! expectations of test suites.
+
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use crate::config::get_config_dir;
+use crate::suite::{Expectation, Verdict};
+
+/// Seconds between two checks of the watchlist, unless configured otherwise
+pub const DEFAULT_WATCH_INTERVAL: u64 = 60;
+
+/// A domain of the watchlist with the verdict of its last check
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watch {
+    pub expectation: Expectation,
+    /// `None` until the first check finished
+    pub verdict: Option<Verdict>,
+}
+
+impl Watch {
+    /// Failing entries come first, passing ones last
+    pub fn severity(&self) -> u8 {
+        match &self.verdict {
+            Some(Verdict::Fail(_)) => 0,
+            Some(Verdict::Error(_)) => 1,
+            None => 2,
+            Some(Verdict::Pass) => 3,
+        }
+    }
+}
+
+/// Change of a watched expectation which is worth a notification
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Flip {
+    /// the expectation does not hold anymore, or did not hold on the first check
+    Failed(String),
+    /// a failing expectation holds again
+    Recovered,
+}
+
+#[derive(Debug, Default)]
+pub struct Watchlist {
+    pub entries: Vec<Watch>,
+    /// file the entries are saved to, they are kept in memory only if `None`
+    path: Option<PathBuf>,
+    /// start of the next round of checks, `None` while a round is running or nothing is watched
+    next_check: Option<Instant>,
+}
+
+impl Watchlist {
+    /// Creates the watchlist, the first round of checks is due immediately
+    pub fn new(path: Option<PathBuf>, expectations: Vec<Expectation>) -> Self {
+        let mut watchlist = Self {
+            entries: expectations
+                .into_iter()
+                .map(|expectation| Watch {
+                    expectation,
+                    verdict: None,
+                })
+                .collect(),
+            path,
+            next_check: None,
+        };
+        watchlist.check_now();
+        watchlist
+    }
+
+    /// Location of the watchlist file, it does not need to exist
+    pub fn path() -> PathBuf {
+        get_config_dir().join("watchlist.yaml")
+    }
+
+    /// Reads the watchlist from `path`, a missing file is an empty watchlist
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let expectations = if path.exists() {
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("could not read watchlist {}", path.display()))?;
+            Self::parse(&content)
+                .with_context(|| format!("invalid watchlist {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+        Ok(Self::new(Some(path), expectations))
+    }
+
+    pub fn parse(content: &str) -> Result<Vec<Expectation>> {
+        if content.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let expectations: Vec<Expectation> = serde_yaml::from_str(content)?;
+        expectations.iter().try_for_each(Expectation::validate)?;
+        Ok(expectations)
+    }
+
+    /// Writes the entries to the watchlist file, if there is one
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let expectations: Vec<&Expectation> = self.entries.iter().map(|w| &w.expectation).collect();
+        let content = serde_yaml::to_string(&expectations)?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("could not create {}", dir.display()))?;
+        }
+        std::fs::write(path, content).with_context(|| format!("could not write {}", path.display()))
+    }
+
+    /// Adds the expectation, it replaces an entry for the same domain and query type
+    pub fn add(&mut self, expectation: Expectation) {
+        let watch = Watch {
+            expectation,
+            verdict: None,
+        };
+        let existing = self.entries.iter_mut().find(|w| {
+            w.expectation.domain == watch.expectation.domain
+                && w.expectation.query_type == watch.expectation.query_type
+        });
+        match existing {
+            Some(existing) => *existing = watch,
+            None => self.entries.push(watch),
+        }
+    }
+
+    /// Removes all entries for the domain, returns whether there were any
+    pub fn remove(&mut self, domain: &str) -> bool {
+        let len = self.entries.len();
+        self.entries
+            .retain(|w| !w.expectation.domain.eq_ignore_ascii_case(domain));
+        if self.entries.is_empty() {
+            self.next_check = None;
+        }
+        self.entries.len() != len
+    }
+
+    pub fn expectations(&self) -> Vec<Expectation> {
+        self.entries.iter().map(|w| w.expectation.clone()).collect()
+    }
+
+    pub fn is_due(&self) -> bool {
+        self.next_check
+            .is_some_and(|next_check| Instant::now() >= next_check)
+    }
+
+    /// A round of checks was started, the next one is scheduled when it finished
+    pub fn started(&mut self) {
+        self.next_check = None;
+    }
+
+    /// Schedules the next round `interval` from now, unless a check is already due
+    pub fn schedule(&mut self, interval: Duration) {
+        if !self.entries.is_empty() && !self.is_due() {
+            self.next_check = Some(Instant::now() + interval);
+        }
+    }
+
+    /// Checks all entries on the next tick, e.g. after the lists were refreshed
+    pub fn check_now(&mut self) {
+        if !self.entries.is_empty() {
+            self.next_check = Some(Instant::now());
+        }
+    }
+
+    /// Stores the verdict of the entry and returns whether it flipped
+    pub fn record(&mut self, expectation: &Expectation, verdict: Verdict) -> Option<Flip> {
+        // the entry may have been removed or replaced while it was checked
+        let watch = self
+            .entries
+            .iter_mut()
+            .find(|w| w.expectation == *expectation)?;
+        match (watch.verdict.replace(verdict.clone()), verdict) {
+            (Some(Verdict::Fail(_)), Verdict::Fail(_)) => None,
+            (_, Verdict::Fail(message)) => Some(Flip::Failed(message)),
+            (Some(Verdict::Fail(_)), Verdict::Pass) => Some(Flip::Recovered),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suite::Outcome;
+
+    #[test]
+    fn test_save_and_load() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("blocky-tui-watchlist-{}", std::process::id()));
+        let path = dir.join("watchlist.yaml");
+        let mut watchlist = Watchlist::load(path.clone())?;
+        assert!(watchlist.entries.is_empty());
+        assert!(!watchlist.is_due());
+
+        watchlist.add(Expectation::new("bank.example", Outcome::Resolved));
+        watchlist.add(Expectation::new("ads.example", Outcome::Blocked));
+        watchlist.add(Expectation::new("bank.example", Outcome::NxDomain));
+        watchlist.save()?;
+
+        let loaded = Watchlist::load(path)?;
+        assert_eq!(loaded.expectations(), watchlist.expectations());
+        assert_eq!(
+            loaded.entries[0].expectation.expect,
+            Some(Outcome::NxDomain)
+        );
+        assert!(loaded.is_due());
+
+        assert!(Watchlist::parse("- domain: bank.example").is_err());
+        assert_eq!(Watchlist::parse("\n")?, []);
+        std::fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_flips_and_scheduling() {
+        let bank = Expectation::new("bank.example", Outcome::Resolved);
+        let mut watchlist = Watchlist::new(None, vec![bank.clone()]);
+        assert!(watchlist.is_due());
+        watchlist.started();
+        assert!(!watchlist.is_due());
+
+        assert_eq!(watchlist.record(&bank, Verdict::Pass), None);
+        let blocked = Verdict::Fail("expected RESOLVED, got BLOCKED (BLOCKED (ads))".to_string());
+        assert_eq!(
+            watchlist.record(&bank, blocked.clone()),
+            Some(Flip::Failed(
+                "expected RESOLVED, got BLOCKED (BLOCKED (ads))".to_string()
+            ))
+        );
+        assert_eq!(watchlist.record(&bank, blocked), None);
+        assert!(matches!(
+            watchlist.entries[0].verdict,
+            Some(Verdict::Fail(_))
+        ));
+        assert_eq!(
+            watchlist.record(&bank, Verdict::Pass),
+            Some(Flip::Recovered)
+        );
+
+        // a requested check is not postponed by the end of the running round
+        watchlist.check_now();
+        watchlist.schedule(Duration::from_secs(60));
+        assert!(watchlist.is_due());
+
+        assert!(watchlist.remove("BANK.example"));
+        assert!(!watchlist.is_due());
+        assert_eq!(watchlist.record(&bank, Verdict::Pass), None);
+    }
+}